@@ -5,9 +5,13 @@ use crate::{
     camera::Cursor,
     drag_drop::{node::grab_node_setup, Draggable},
     instruction::*,
+    keymap::{Action, Keymap},
     mouse_interaction::select::{ReplaceSelection, Selected},
+    node_registry::{self, NodeTypeInfo},
+    scan_code_input::{ScanCode, ScanCodeInput},
     shared::NodeIdComponent,
     sync_graph::NODE_SIZE,
+    text_input::{TextField, TextInput, TextInputEvent, Validation},
     undo::{node::AddNode, prelude::*},
     AmbiguitySet, CustomStage, GrabToolType, ToolState,
 };
@@ -19,6 +23,13 @@ use vismut_core::{
     node::{node_type::NodeType, Node},
 };
 
+/// The file extensions that can be imported as `Image` nodes.
+pub(crate) const IMAGE_EXTENSIONS: [&str; 9] = [
+    "bmp", "exr", "gif", "hdr", "jpg", "jpeg", "png", "tga", "tiff",
+];
+/// The vertical distance between nodes that are created at the same time.
+pub(crate) const MULTI_IMPORT_SPACING: f32 = NODE_SIZE + 12.0;
+
 #[derive(Copy, Clone, Debug)]
 struct DragToolUndo;
 impl UndoCommand for DragToolUndo {
@@ -89,6 +100,41 @@ impl UndoCommand for SelectedToCursorSneaky {
     }
 }
 
+/// Moves the selected nodes so their translations become relative to the cursor, which keeps
+/// their layout intact.
+///
+/// The sneaky variant is not saved on the undo stack. Can probably be replaced with a command that
+/// removes the most recent command from the undo stack.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct OffsetSelectedByCursorSneaky;
+impl UndoCommand for OffsetSelectedByCursorSneaky {
+    fn command_type(&self) -> crate::undo::UndoCommandType {
+        crate::undo::UndoCommandType::Custom
+    }
+
+    fn forward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        let mut query =
+            world.query_filtered::<(&mut Transform, &mut GlobalTransform), (With<Selected>, With<NodeIdComponent>)>();
+        let cursor = *world
+            .query_filtered::<&GlobalTransform, With<Cursor>>()
+            .iter(world)
+            .next()
+            .unwrap();
+
+        for (mut transform, mut global_transform) in query.iter_mut(world) {
+            transform.translation.x += cursor.translation.x;
+            transform.translation.y += cursor.translation.y;
+
+            global_transform.translation.x = transform.translation.x;
+            global_transform.translation.y = transform.translation.y;
+        }
+    }
+
+    fn backward(&self, _: &mut World, _: &mut UndoCommandManager) {
+        unreachable!("this command is not saved on the undo stack");
+    }
+}
+
 /// The sneaky variant is not saved on the undo stack. Can probably be replaced with a command that
 /// removes the most recent command from the undo stack.
 #[derive(Copy, Clone, Debug)]
@@ -120,8 +166,6 @@ impl UndoCommand for MultiImportOffset {
     }
 
     fn forward(&self, world: &mut World, _: &mut UndoCommandManager) {
-        const NODE_OFFSET: f32 = NODE_SIZE + 12.0;
-
         let cursor_transform = *world
             .query_filtered::<&GlobalTransform, With<Cursor>>()
             .iter(world)
@@ -135,7 +179,7 @@ impl UndoCommand for MultiImportOffset {
             let new_translation = {
                 let mut translation = transform.translation;
                 translation.x = 0.0;
-                translation.y = NODE_OFFSET * i as f32;
+                translation.y = MULTI_IMPORT_SPACING * i as f32;
                 translation
             };
             let new_global_translation = cursor_transform.translation - new_translation;
@@ -154,7 +198,9 @@ pub(crate) struct AddToolPlugin;
 
 impl Plugin for AddToolPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+        app.insert_resource(AddPalette::default())
+            .insert_resource(RecentNodeTypes::default())
+            .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
             .add_system_set_to_stage(
                 CoreStage::Update,
                 SystemSet::new()
@@ -172,6 +218,12 @@ impl Plugin for AddToolPlugin {
                             .with_run_criteria(State::on_update(ToolState::Add))
                             .in_ambiguity_set(AmbiguitySet),
                     )
+                    .with_system(
+                        add_exit
+                            .system()
+                            .with_run_criteria(State::on_exit(ToolState::Add))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
                     .with_system(
                         grab_tool_add_instructions
                             .system()
@@ -194,114 +246,276 @@ impl Plugin for AddToolPlugin {
     }
 }
 
-fn setup(mut tool_list: ResMut<ToolList>) {
-    tool_list.insert("Shift A: Add node".to_string());
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Add));
+}
+
+/// How many recently added node types are remembered.
+const RECENT_COUNT: usize = 5;
+
+/// Which of the node types matching the search is chosen. The search is typed in the
+/// `TextField`.
+#[derive(Debug, Default)]
+struct AddPalette {
+    chosen: usize,
+}
+
+/// The names of the most recently added node types, the most recent first.
+#[derive(Debug, Default)]
+struct RecentNodeTypes(Vec<&'static str>);
+
+impl RecentNodeTypes {
+    fn add(&mut self, name: &'static str) {
+        self.0.retain(|recent| *recent != name);
+        self.0.insert(0, name);
+        self.0.truncate(RECENT_COUNT);
+    }
+
+    /// Lower is more recent, and node types that haven't been added are last.
+    fn rank(&self, name: &str) -> usize {
+        self.0
+            .iter()
+            .position(|recent| *recent == name)
+            .unwrap_or(RECENT_COUNT)
+    }
+}
+
+/// Scores how well `query` matches `candidate`, higher is better. Every character in the query has
+/// to be in the candidate in the same order, and consecutive characters and characters at the
+/// start of words score higher. Returns `None` if it doesn't match.
+fn fuzzy_score(query: &str, candidate: &str) -> Option<i32> {
+    let candidate: Vec<char> = candidate.to_lowercase().chars().collect();
+    let mut score = 0;
+    let mut position = 0;
+    let mut previous_match: Option<usize> = None;
+
+    for query_char in query.to_lowercase().chars().filter(|c| !c.is_whitespace()) {
+        let found = candidate[position..]
+            .iter()
+            .position(|c| *c == query_char)
+            .map(|offset| position + offset)?;
+
+        score += 1;
+        if previous_match.map_or(false, |previous| previous + 1 == found) {
+            score += 5;
+        }
+        if found == 0 || candidate[found - 1] == ' ' {
+            score += 10;
+        }
+
+        previous_match = Some(found);
+        position = found + 1;
+    }
+
+    Some(score)
+}
+
+/// The node types that match the query, the best matches first. Names are preferred over search
+/// terms, and recently added node types win ties.
+fn matching_node_types(query: &str, recent: &RecentNodeTypes) -> Vec<&'static NodeTypeInfo> {
+    let mut matches: Vec<(i32, usize, &'static NodeTypeInfo)> = node_registry::addable()
+        .filter_map(|info| {
+            let score = fuzzy_score(query, info.name)
+                .map(|score| score * 2)
+                .max(fuzzy_score(
+                    query,
+                    &format!("{} {}", info.name, info.search_terms),
+                ))?;
+            Some((score, recent.rank(info.name), info))
+        })
+        .collect();
+
+    matches.sort_by(|(score_a, recent_a, info_a), (score_b, recent_b, info_b)| {
+        score_b
+            .cmp(score_a)
+            .then(recent_a.cmp(recent_b))
+            .then(info_a.name.cmp(info_b.name))
+    });
+
+    matches.into_iter().map(|(_, _, info)| info).collect()
+}
+
+fn show_palette(
+    instructions: &mut Instructions,
+    palette: &AddPalette,
+    input: &TextInput,
+    matches: &[&'static NodeTypeInfo],
+) {
+    let mut text = format!("## Add node\nSearch: {}\n\n", input.display());
+
+    if matches.is_empty() {
+        text.push_str("No matches\n");
+    }
+    for (i, info) in matches.iter().enumerate() {
+        let marker = if i == palette.chosen { ">" } else { " " };
+        text = format!("{}{} {} ({})\n", text, marker, info.name, info.category);
+    }
+    text.push_str("\nUp/Down: Choose\nEnter: Add");
+
+    instructions.insert(InstructId::Tool, text);
 }
 
-fn add_tool_instructions(mut instructions: ResMut<Instructions>) {
-    instructions.insert(
-        InstructId::Tool,
-        "I: Image\nS: Separate\nC: Combine\nO: Output\nV: Value\n"
-            // "C: Combine\nI: Input\nM: Mix\nN: Height to Normal\nO: Output\nV: Value\nS: Separate\n"
-            .to_string(),
+fn add_tool_instructions(
+    mut instructions: ResMut<Instructions>,
+    mut palette: ResMut<AddPalette>,
+    mut text_field: ResMut<TextField>,
+    recent: Res<RecentNodeTypes>,
+) {
+    let input = TextInput::new("", Validation::Text);
+    *palette = AddPalette::default();
+    show_palette(
+        &mut instructions,
+        &palette,
+        &input,
+        &matching_node_types(&input.text(), &recent),
     );
+    **text_field = Some(input);
 }
 
-/// When you press the button for a node it creates that node for you.
+fn add_exit(mut text_field: ResMut<TextField>) {
+    **text_field = None;
+}
+
+/// Type to search for a node type, and press enter to add the chosen one.
+#[allow(clippy::too_many_arguments)]
 fn add_update(
     mut char_input_events: EventReader<ReceivedCharacter>,
+    mut scan_code_input: ResMut<ScanCodeInput>,
     mut tool_state: ResMut<State<ToolState>>,
+    mut instructions: ResMut<Instructions>,
+    mut palette: ResMut<AddPalette>,
+    mut text_field: ResMut<TextField>,
+    mut recent: ResMut<RecentNodeTypes>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
     mut undo_command_manager: ResMut<UndoCommandManager>,
 ) {
-    let mut done = false;
-
-    for event in char_input_events.iter() {
-        let node_types: Vec<NodeType> = match event.char.to_ascii_lowercase() {
-            'c' => vec![NodeType::CombineRgba],
-            'i' => {
-                let file_dialog = FileDialog::new()
-                    .add_filter(
-                        "Image",
-                        &["bmp", "gif", "jpg", "jpeg", "png", "tga", "tiff"],
-                    )
-                    .show_open_multiple_file();
-
-                if let Ok(path_bufs) = file_dialog {
-                    path_bufs.into_iter().map(NodeType::Image).collect()
-                } else {
-                    error!("could not open file dialog");
-                    done = true;
-                    Vec::new()
-                }
-            }
-            // 'm' => vec![NodeType::Mix(MixType::Add)],
-            // 'n' => vec![NodeType::HeightToNormal],
-            'o' => {
-                // let path = FileDialog::new()
-                //     // .set_location("~/Desktop")
-                //     .add_filter("PNG Image", &["png"])
-                //     .show_save_single_file()
-                //     .unwrap();
-
-                // let path = match path {
-                //     Some(path) => path,
-                //     None => {
-                //         println!("Error: Invalid open file path");
-                //         return;
-                //     }
-                // };
-
-                vec![NodeType::OutputRgba("untitled".into())]
+    let input = match text_field.0.as_mut() {
+        Some(input) => input,
+        None => return,
+    };
+
+    let query_before = input.text();
+    let display_before = input.display();
+    let confirmed = match input.update(&mut char_input_events, &mut scan_code_input) {
+        Some(TextInputEvent::Submit(_)) => true,
+        Some(TextInputEvent::Cancel) => {
+            tool_state.overwrite_replace(ToolState::None).unwrap();
+            return;
+        }
+        None => false,
+    };
+    let query = input.text();
+    let mut changed = input.display() != display_before;
+    if query != query_before {
+        palette.chosen = 0;
+    }
+
+    let matches = matching_node_types(&query, &recent);
+
+    if scan_code_input.clear_just_pressed(ScanCode::ArrowDown) && !matches.is_empty() {
+        palette.chosen = (palette.chosen + 1) % matches.len();
+        changed = true;
+    }
+    if scan_code_input.clear_just_pressed(ScanCode::ArrowUp) && !matches.is_empty() {
+        palette.chosen = (palette.chosen + matches.len() - 1) % matches.len();
+        changed = true;
+    }
+
+    let chosen = match matches.get(palette.chosen).copied() {
+        Some(info) if confirmed => info,
+        _ => {
+            if changed {
+                show_palette(&mut instructions, &palette, input, &matches);
             }
-            's' => vec![NodeType::SeparateRgba],
-            'v' => vec![NodeType::Value(1.0)],
-            _ => Vec::new(),
-        };
-
-        if !node_types.is_empty() {
-            let mut created_nodes: usize = 0;
-
-            for node_type in node_types {
-                if create_node(&mut undo_command_manager, &*live_graph, &node_type).is_ok() {
-                    created_nodes += 1;
-                } else {
-                    warn!("failed to create node: {:?}", node_type);
-                }
+            return;
+        }
+    };
+
+    let node_type = match chosen.default {
+        Some(default) => default(),
+        None => return,
+    };
+    let node_types: Vec<NodeType> = if let NodeType::Image(_) = node_type {
+        scan_code_input.reset_all();
+        match FileDialog::new()
+            .add_filter("Image", &IMAGE_EXTENSIONS)
+            .show_open_multiple_file()
+        {
+            Ok(path_bufs) => path_bufs.into_iter().map(NodeType::Image).collect(),
+            Err(e) => {
+                error!("could not open file dialog: {:?}", e);
+                Vec::new()
             }
+        }
+    } else {
+        vec![node_type]
+    };
 
-            grab_new_nodes(&mut undo_command_manager);
+    if node_types.is_empty() {
+        tool_state.overwrite_replace(ToolState::None).unwrap();
+        return;
+    }
+    recent.add(chosen.name);
 
-            if created_nodes > 1 {
-                undo_command_manager.push(Box::new(MultiImportOffset));
-            }
+    let mut created_nodes: usize = 0;
 
-            break;
-        } else if done {
-            tool_state.overwrite_replace(ToolState::None).unwrap();
-            break;
+    for node_type in node_types {
+        if create_node(&mut undo_command_manager, &*live_graph, &node_type).is_ok() {
+            created_nodes += 1;
+        } else {
+            warn!("failed to create node: {:?}", node_type);
         }
     }
+
+    grab_new_nodes(&mut undo_command_manager);
+
+    if created_nodes > 1 {
+        undo_command_manager.push(Box::new(MultiImportOffset));
+    }
 }
 
 pub fn create_node(
     undo_command_manager: &mut UndoCommandManager,
     live_graph: &Arc<RwLock<LiveGraph>>,
     node_type: &NodeType,
+) -> Result<()> {
+    create_node_at(undo_command_manager, live_graph, node_type, Vec2::ZERO)
+}
+
+/// Like `create_node`, but the node is placed at `translation` instead of being moved there
+/// afterwards, so it ends up in the right place when it's redone.
+pub fn create_node_at(
+    undo_command_manager: &mut UndoCommandManager,
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    node_type: &NodeType,
+    translation: Vec2,
 ) -> Result<()> {
     let node = create_default_node(live_graph, node_type.clone())?;
 
-    undo_command_manager.push(Box::new(AddNode::new(node, Vec2::ZERO)));
+    undo_command_manager.push(Box::new(AddNode::new(node, translation)));
 
     Ok(())
 }
 
+/// Replaces the selection with the nodes that were just created.
+pub fn select_new_nodes(undo_command_manager: &mut UndoCommandManager) {
+    undo_command_manager.push(Box::new(SelectNew));
+}
+
 pub fn grab_new_nodes(undo_command_manager: &mut UndoCommandManager) {
     undo_command_manager.push(Box::new(SelectNew));
     undo_command_manager.push(Box::new(SelectedToCursorSneaky));
     undo_command_manager.push(Box::new(DragToolUndo));
 }
 
+/// Like `grab_new_nodes`, but the new nodes keep their layout around the cursor instead of all
+/// being placed on it. The nodes should be added with translations relative to the cursor.
+pub fn grab_new_nodes_keep_layout(undo_command_manager: &mut UndoCommandManager) {
+    undo_command_manager.push(Box::new(SelectNew));
+    undo_command_manager.push(Box::new(OffsetSelectedByCursorSneaky));
+    undo_command_manager.push(Box::new(DragToolUndo));
+}
+
 pub fn create_default_node(
     live_graph: &Arc<RwLock<LiveGraph>>,
     node_type: NodeType,
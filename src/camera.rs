@@ -1,8 +1,28 @@
 /// Box select tool
-use crate::{instruction::*, AmbiguitySet, CustomStage, Workspace};
-use bevy::prelude::*;
+use crate::{
+    instruction::*,
+    keymap::{Action, Keymap},
+    shared::NodeIdComponent,
+    sync_graph::NODE_SIZE,
+    workspace::cursor_to_world,
+    AmbiguitySet, CustomStage, Selected, ToolState, Workspace,
+};
+use bevy::{
+    input::mouse::{MouseScrollUnit, MouseWheel},
+    prelude::*,
+};
 
 pub(crate) const CAMERA_DISTANCE: f32 = 10.;
+/// The smallest allowed scale of the workspace camera's projection, the most zoomed in.
+const ZOOM_MIN: f32 = 0.25;
+/// The largest allowed scale of the workspace camera's projection, the most zoomed out.
+const ZOOM_MAX: f32 = 8.0;
+/// How much the projection is scaled for each line scrolled.
+const ZOOM_STEP: f32 = 1.2;
+/// Touchpads scroll in pixels, this is how many of them count as one line.
+const PIXELS_PER_LINE: f32 = 50.0;
+/// How much space to leave around the nodes when framing them.
+const FRAME_MARGIN: f32 = 1.1;
 
 #[derive(Component)]
 pub(crate) struct WorkspaceCameraAnchor;
@@ -67,6 +87,30 @@ impl Plugin for CameraPlugin {
                             .system()
                             .with_run_criteria(State::on_update(FirstPersonState::Off))
                             .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        scroll_zoom
+                            .system()
+                            .with_run_criteria(State::on_update(FirstPersonState::Off))
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            )
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Update)
+                    .after(CustomStage::Setup)
+                    .with_system(
+                        frame_selected
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::Frame(false)))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        frame_all
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::Frame(true)))
+                            .in_ambiguity_set(AmbiguitySet),
                     ),
             );
     }
@@ -74,10 +118,18 @@ impl Plugin for CameraPlugin {
 
 fn setup(
     mut instructions: ResMut<Instructions>,
+    mut tool_list: ResMut<ToolList>,
+    keymap: Res<Keymap>,
     mut commands: Commands,
     asset_server: Res<AssetServer>,
 ) {
-    instructions.insert(InstructId::FirstPerson, first_person_instruct(false));
+    instructions.insert(
+        InstructId::FirstPerson,
+        first_person_instruct(&keymap, false),
+    );
+    tool_list.insert("Scroll: Zoom".to_string());
+    tool_list.insert(keymap.help(Action::FrameSelected));
+    tool_list.insert(keymap.help(Action::FrameAll));
 
     commands
         .spawn_bundle(OrthographicCameraBundle::new_2d())
@@ -143,11 +195,10 @@ fn first_person_off_update(
     }
 }
 
-fn first_person_instruct(on: bool) -> String {
-    const TEXT: &str = "`: First person - ";
+fn first_person_instruct(keymap: &Keymap, on: bool) -> String {
     let setting = if on { &"On" } else { &"Off" };
 
-    format!("{}{}\n\n", TEXT, setting)
+    format!("{} - {}\n\n", keymap.help(Action::FirstPerson), setting)
 }
 
 // fn first_person_on_setup(
@@ -158,7 +209,7 @@ fn first_person_instruct(on: bool) -> String {
 //     mut q_crosshair: Query<&mut Visibility, With<Crosshair>>,
 //     mut commands: Commands,
 // ) {
-//     instruction_list.insert(InstructId::FirstPerson, first_person_instruct(true));
+//     instruction_list.insert(InstructId::FirstPerson, first_person_instruct(&keymap, true));
 
 //     let window = windows.get_primary_mut().unwrap();
 //     window.set_cursor_visibility(false);
@@ -183,7 +234,7 @@ fn first_person_instruct(on: bool) -> String {
 //     mut q_crosshair: Query<&mut Visibility, With<Crosshair>>,
 //     mut commands: Commands,
 // ) {
-//     instructions.insert(InstructId::FirstPerson, first_person_instruct(false));
+//     instructions.insert(InstructId::FirstPerson, first_person_instruct(&keymap, false));
 
 //     let window = windows.get_primary_mut().unwrap();
 //     window.set_cursor_visibility(true);
@@ -201,16 +252,126 @@ fn first_person_instruct(on: bool) -> String {
 fn mouse_pan(
     workspace: Res<Workspace>,
     windows: Res<Windows>,
-    mut camera: Query<&mut Transform, With<WorkspaceCamera>>,
+    mut camera: Query<(&mut Transform, &OrthographicProjection), With<WorkspaceCamera>>,
     i_mouse_button: Res<Input<MouseButton>>,
 ) {
     if i_mouse_button.pressed(MouseButton::Middle) && workspace.cursor_moved {
         let window = windows.get_primary().unwrap();
         let scale = window.backend_scale_factor();
 
-        if let Ok(mut camera_t) = camera.get_single_mut() {
-            camera_t.translation.x -= workspace.cursor_delta.x * (1.0 / scale as f32);
-            camera_t.translation.y += workspace.cursor_delta.y * (1.0 / scale as f32);
+        if let Ok((mut camera_t, projection)) = camera.get_single_mut() {
+            let factor = projection.scale / scale as f32;
+            camera_t.translation.x -= workspace.cursor_delta.x * factor;
+            camera_t.translation.y += workspace.cursor_delta.y * factor;
         }
     }
 }
+
+/// Zoom using the scroll wheel, keeping the point under the cursor in place.
+fn scroll_zoom(
+    mut er_mouse_wheel: EventReader<MouseWheel>,
+    workspace: Res<Workspace>,
+    windows: Res<Windows>,
+    mut camera: Query<(&mut Transform, &mut OrthographicProjection), With<WorkspaceCamera>>,
+) {
+    let scroll: f32 = er_mouse_wheel
+        .iter()
+        .map(|event| match event.unit {
+            MouseScrollUnit::Line => event.y,
+            MouseScrollUnit::Pixel => event.y / PIXELS_PER_LINE,
+        })
+        .sum();
+    if scroll == 0.0 {
+        return;
+    }
+
+    let window = windows.get_primary().unwrap();
+
+    if let Ok((mut camera_t, mut projection)) = camera.get_single_mut() {
+        let cursor_before =
+            cursor_to_world(window, &camera_t, &projection, workspace.cursor_screen);
+        projection.scale = (projection.scale * ZOOM_STEP.powf(-scroll)).clamp(ZOOM_MIN, ZOOM_MAX);
+        let cursor_after = cursor_to_world(window, &camera_t, &projection, workspace.cursor_screen);
+
+        camera_t.translation += (cursor_before - cursor_after).extend(0.0);
+    }
+}
+
+fn frame_selected(
+    mut tool_state: ResMut<State<ToolState>>,
+    windows: Res<Windows>,
+    q_node: Query<(&Transform, Option<&Selected>), With<NodeIdComponent>>,
+    mut camera: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        (With<WorkspaceCamera>, Without<NodeIdComponent>),
+    >,
+) {
+    let selected = q_node
+        .iter()
+        .filter(|(_, selected)| selected.is_some())
+        .map(|(transform, _)| transform.translation.truncate())
+        .collect::<Vec<Vec2>>();
+
+    // Frame everything if nothing is selected.
+    if selected.is_empty() {
+        let all = q_node
+            .iter()
+            .map(|(transform, _)| transform.translation.truncate());
+        frame_nodes(all, &*windows, &mut camera);
+    } else {
+        frame_nodes(selected.into_iter(), &*windows, &mut camera);
+    }
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+fn frame_all(
+    mut tool_state: ResMut<State<ToolState>>,
+    windows: Res<Windows>,
+    q_node: Query<&Transform, With<NodeIdComponent>>,
+    mut camera: Query<
+        (&mut Transform, &mut OrthographicProjection),
+        (With<WorkspaceCamera>, Without<NodeIdComponent>),
+    >,
+) {
+    let all = q_node
+        .iter()
+        .map(|transform| transform.translation.truncate());
+    frame_nodes(all, &*windows, &mut camera);
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+/// Centers the camera on the nodes at the given translations, and zooms so they all fit in the
+/// window.
+fn frame_nodes(
+    translations: impl Iterator<Item = Vec2>,
+    windows: &Windows,
+    camera: &mut Query<
+        (&mut Transform, &mut OrthographicProjection),
+        (With<WorkspaceCamera>, Without<NodeIdComponent>),
+    >,
+) {
+    let half_node = Vec2::splat(NODE_SIZE / 2.0);
+    let bounds = translations.fold(None, |bounds: Option<(Vec2, Vec2)>, translation| {
+        let (min, max) = bounds.unwrap_or((translation, translation));
+        Some((min.min(translation), max.max(translation)))
+    });
+
+    let (min, max) = match bounds {
+        Some((min, max)) => (min - half_node, max + half_node),
+        None => return,
+    };
+    let window = windows.get_primary().unwrap();
+    let size = max - min;
+
+    if let Ok((mut camera_t, mut projection)) = camera.get_single_mut() {
+        let center = (min + max) / 2.0;
+        camera_t.translation.x = center.x;
+        camera_t.translation.y = center.y;
+
+        projection.scale = (f32::max(size.x / window.width(), size.y / window.height())
+            * FRAME_MARGIN)
+            .clamp(ZOOM_MIN, ZOOM_MAX);
+    }
+}
@@ -0,0 +1,374 @@
+/// Copying, cutting, pasting and duplicating nodes.
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, bail, Result};
+use arboard::Clipboard;
+use bevy::prelude::*;
+use vismut_core::{edge::Edge, live_graph::LiveGraph, node_graph::NodeId};
+
+use crate::{
+    add_tool::grab_new_nodes_keep_layout,
+    delete_tool::DeleteSelected,
+    embed::EmbeddedImages,
+    export_format::ExportFormats,
+    group::GroupContents,
+    instruction::ToolList,
+    keymap::{Action, Keymap},
+    normal_map::NormalMaps,
+    notification::Notifications,
+    project::Project,
+    shared::NodeIdComponent,
+    sync_graph::{Edge as GuiEdge, InputCounts},
+    thumbnail::ThumbnailExposures,
+    undo::{edge::AddEdge, node::AddNode, prelude::*},
+    AmbiguitySet, CustomStage, Selected, ToolState,
+};
+
+pub(crate) struct ClipboardPlugin;
+
+impl Plugin for ClipboardPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Update)
+                    .after(CustomStage::Setup)
+                    .with_system(
+                        copy.system()
+                            .with_run_criteria(State::on_enter(ToolState::Copy))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        cut.system()
+                            .with_run_criteria(State::on_enter(ToolState::Cut))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        paste
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::Paste))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        duplicate
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::Duplicate))
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            );
+    }
+}
+
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Copy));
+    tool_list.insert(keymap.help(Action::Cut));
+    tool_list.insert(keymap.help(Action::Paste));
+    tool_list.insert(keymap.help(Action::Duplicate));
+}
+
+/// Creates a `Project` containing the selected nodes and the edges between them. The translations
+/// are relative to the center of the selection.
+#[allow(clippy::too_many_arguments)]
+fn selection_to_project(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    input_counts: &InputCounts,
+    export_formats: &ExportFormats,
+    embedded_images: &EmbeddedImages,
+    thumbnail_exposures: &ThumbnailExposures,
+    normal_maps: &NormalMaps,
+    group_contents: &GroupContents,
+    q_selected: &Query<(&NodeIdComponent, &Transform), With<Selected>>,
+    q_edge: &Query<&GuiEdge>,
+) -> Result<Project> {
+    let nodes = q_selected
+        .iter()
+        .map(|(node_id, transform)| (node_id.0, transform.translation.truncate()))
+        .collect::<Vec<(NodeId, Vec2)>>();
+    if nodes.is_empty() {
+        bail!("no nodes are selected");
+    }
+
+    let center = nodes
+        .iter()
+        .fold(Vec2::ZERO, |sum, (_, translation)| sum + *translation)
+        / nodes.len() as f32;
+    let edges = q_edge
+        .iter()
+        .map(|gui_edge| Edge::from(*gui_edge))
+        .filter(|edge| {
+            nodes.iter().any(|(node_id, _)| *node_id == edge.output_id)
+                && nodes.iter().any(|(node_id, _)| *node_id == edge.input_id)
+        })
+        .collect();
+
+    let live_graph = live_graph.read().map_err(|e| anyhow!("{}", e))?;
+    Project::new(
+        &live_graph,
+        input_counts,
+        export_formats,
+        embedded_images,
+        thumbnail_exposures,
+        normal_maps,
+        group_contents,
+        nodes
+            .into_iter()
+            .map(|(node_id, translation)| (node_id, translation - center)),
+        edges,
+    )
+}
+
+#[allow(clippy::too_many_arguments)]
+fn copy_to_clipboard(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    input_counts: &InputCounts,
+    export_formats: &ExportFormats,
+    embedded_images: &EmbeddedImages,
+    thumbnail_exposures: &ThumbnailExposures,
+    normal_maps: &NormalMaps,
+    group_contents: &GroupContents,
+    q_selected: &Query<(&NodeIdComponent, &Transform), With<Selected>>,
+    q_edge: &Query<&GuiEdge>,
+) -> Result<()> {
+    let json = selection_to_project(
+        live_graph,
+        input_counts,
+        export_formats,
+        embedded_images,
+        thumbnail_exposures,
+        normal_maps,
+        group_contents,
+        q_selected,
+        q_edge,
+    )?
+    .to_json()?;
+    Clipboard::new()?.set_text(json)?;
+    Ok(())
+}
+
+/// Adds the nodes and edges in the `Project` with fresh ids, and grabs them.
+#[allow(clippy::too_many_arguments)]
+fn paste_project(
+    mut project: Project,
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    input_counts: &mut InputCounts,
+    export_formats: &mut ExportFormats,
+    embedded_images: &mut EmbeddedImages,
+    thumbnail_exposures: &mut ThumbnailExposures,
+    normal_maps: &mut NormalMaps,
+    group_contents: &mut GroupContents,
+    undo_command_manager: &mut UndoCommandManager,
+) -> Result<()> {
+    if project.nodes.is_empty() {
+        bail!("there are no nodes to paste");
+    }
+
+    let mut new_ids = HashMap::new();
+    let mut nodes = Vec::new();
+    {
+        let mut live_graph = live_graph.write().map_err(|e| anyhow!("{}", e))?;
+        project.embed_images(&mut live_graph, embedded_images)?;
+        for project_node in &project.nodes {
+            new_ids.insert(project_node.node.node_id, live_graph.new_id());
+            nodes.push(project_node.node(&mut live_graph)?);
+        }
+    }
+
+    for (project_node, mut node) in project.nodes.into_iter().zip(nodes) {
+        node.node_id = new_ids[&node.node_id];
+
+        if let Some(input_count) = project_node.input_count {
+            input_counts.insert(node.node_id, input_count);
+        }
+        if let Some(export_format) = project_node.export_format {
+            export_formats.insert(node.node_id, export_format);
+        }
+        if let Some(exposure) = project_node.thumbnail_exposure {
+            thumbnail_exposures.insert(node.node_id, exposure);
+        }
+        if let Some(normal_map) = project_node.normal_map {
+            normal_maps.insert(node.node_id, normal_map);
+        }
+        if let Some(group) = project_node.group {
+            group_contents.insert(node.node_id, *group);
+        }
+
+        undo_command_manager.push(Box::new(AddNode::new(
+            node,
+            project_node.translation.into(),
+        )));
+    }
+
+    for edge in project.edges {
+        if let (Some(output_id), Some(input_id)) =
+            (new_ids.get(&edge.output_id), new_ids.get(&edge.input_id))
+        {
+            undo_command_manager.push(Box::new(AddEdge(Edge {
+                output_id: *output_id,
+                input_id: *input_id,
+                ..edge
+            })));
+        }
+    }
+
+    grab_new_nodes_keep_layout(undo_command_manager);
+
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+fn copy(
+    mut tool_state: ResMut<State<ToolState>>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    input_counts: Res<InputCounts>,
+    export_formats: Res<ExportFormats>,
+    embedded_images: Res<EmbeddedImages>,
+    thumbnail_exposures: Res<ThumbnailExposures>,
+    normal_maps: Res<NormalMaps>,
+    group_contents: Res<GroupContents>,
+    q_selected: Query<(&NodeIdComponent, &Transform), With<Selected>>,
+    q_edge: Query<&GuiEdge>,
+    mut notifications: ResMut<Notifications>,
+) {
+    if let Err(e) = copy_to_clipboard(
+        &*live_graph,
+        &*input_counts,
+        &*export_formats,
+        &*embedded_images,
+        &*thumbnail_exposures,
+        &*normal_maps,
+        &*group_contents,
+        &q_selected,
+        &q_edge,
+    ) {
+        notifications.error(format!("Unable to copy: {}", e));
+    }
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn cut(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    input_counts: Res<InputCounts>,
+    export_formats: Res<ExportFormats>,
+    embedded_images: Res<EmbeddedImages>,
+    thumbnail_exposures: Res<ThumbnailExposures>,
+    normal_maps: Res<NormalMaps>,
+    group_contents: Res<GroupContents>,
+    q_selected: Query<(&NodeIdComponent, &Transform), With<Selected>>,
+    q_edge: Query<&GuiEdge>,
+    mut notifications: ResMut<Notifications>,
+) {
+    match copy_to_clipboard(
+        &*live_graph,
+        &*input_counts,
+        &*export_formats,
+        &*embedded_images,
+        &*thumbnail_exposures,
+        &*normal_maps,
+        &*group_contents,
+        &q_selected,
+        &q_edge,
+    ) {
+        Ok(_) => {
+            undo_command_manager.push(Box::new(DeleteSelected));
+            undo_command_manager.push(Box::new(Checkpoint));
+        }
+        Err(e) => notifications.error(format!("Unable to cut: {}", e)),
+    }
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+#[allow(clippy::too_many_arguments)]
+fn paste(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    mut input_counts: ResMut<InputCounts>,
+    mut export_formats: ResMut<ExportFormats>,
+    mut embedded_images: ResMut<EmbeddedImages>,
+    mut thumbnail_exposures: ResMut<ThumbnailExposures>,
+    mut normal_maps: ResMut<NormalMaps>,
+    mut group_contents: ResMut<GroupContents>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let result = Clipboard::new()
+        .and_then(|mut clipboard| clipboard.get_text())
+        .map_err(anyhow::Error::from)
+        .and_then(|text| Project::from_json(&text))
+        .and_then(|project| {
+            paste_project(
+                project,
+                &*live_graph,
+                &mut *input_counts,
+                &mut *export_formats,
+                &mut *embedded_images,
+                &mut *thumbnail_exposures,
+                &mut *normal_maps,
+                &mut *group_contents,
+                &mut *undo_command_manager,
+            )
+        });
+
+    match result {
+        // The grab tool takes over from here.
+        Ok(_) => (),
+        Err(e) => {
+            notifications.error(format!("Unable to paste: {}", e));
+            tool_state.overwrite_replace(ToolState::None).unwrap();
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn duplicate(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    mut input_counts: ResMut<InputCounts>,
+    mut export_formats: ResMut<ExportFormats>,
+    mut embedded_images: ResMut<EmbeddedImages>,
+    mut thumbnail_exposures: ResMut<ThumbnailExposures>,
+    mut normal_maps: ResMut<NormalMaps>,
+    mut group_contents: ResMut<GroupContents>,
+    q_selected: Query<(&NodeIdComponent, &Transform), With<Selected>>,
+    q_edge: Query<&GuiEdge>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let result = selection_to_project(
+        &*live_graph,
+        &*input_counts,
+        &*export_formats,
+        &*embedded_images,
+        &*thumbnail_exposures,
+        &*normal_maps,
+        &*group_contents,
+        &q_selected,
+        &q_edge,
+    )
+    .and_then(|project| {
+        paste_project(
+            project,
+            &*live_graph,
+            &mut *input_counts,
+            &mut *export_formats,
+            &mut *embedded_images,
+            &mut *thumbnail_exposures,
+            &mut *normal_maps,
+            &mut *group_contents,
+            &mut *undo_command_manager,
+        )
+    });
+
+    if let Err(e) = result {
+        notifications.error(format!("Unable to duplicate: {}", e));
+        tool_state.overwrite_replace(ToolState::None).unwrap();
+    }
+}
@@ -8,6 +8,17 @@ use vismut_core::{
     slot_data::ChannelPixel,
 };
 
+/// Makes a node and everything depending on it dirty, so it's processed again.
+///
+/// This sets the node's `NodeType` to what it already is, the same way an edit does. It relies on
+/// the core marking a node and its children dirty whenever it's taken with `LiveGraph::node_mut`,
+/// and on `Image` nodes reading their file each time they are processed rather than keeping the
+/// pixels from the last time.
+pub(crate) fn reprocess(live_graph: &mut LiveGraph, node_id: NodeId) -> Result<()> {
+    let node_type: NodeType = node_id.get(live_graph)?;
+    node_id.set(live_graph, node_type)
+}
+
 pub trait Translator<DataType>: Debug {
     fn get(&self, live_graph: &LiveGraph) -> Result<DataType>;
     fn set(&self, live_graph: &mut LiveGraph, value: DataType) -> Result<()>;
@@ -74,7 +85,7 @@ impl Translator<String> for NodeId {
     fn get(&self, live_graph: &LiveGraph) -> Result<String> {
         let node = live_graph.node(*self)?;
 
-        if let NodeType::OutputRgba(name) = node.node_type {
+        if let NodeType::OutputRgba(name) | NodeType::OutputGray(name) = node.node_type {
             Ok(name)
         } else {
             bail!("wrong NodeType: {:?}", node.node_type)
@@ -5,6 +5,7 @@ use vismut_core::live_graph::LiveGraph;
 
 use crate::{
     instruction::ToolList,
+    keymap::{Action, Keymap},
     shared::NodeIdComponent,
     undo::{node::RemoveNode, prelude::*},
     AmbiguitySet, Selected,
@@ -18,8 +19,8 @@ impl Plugin for DeleteToolPlugin {
     }
 }
 
-fn setup(mut tool_list: ResMut<ToolList>) {
-    tool_list.insert("X: Delete selected".to_string());
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Delete));
 }
 
 #[derive(Copy, Clone, Debug)]
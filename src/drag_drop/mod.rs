@@ -1,7 +1,11 @@
 pub mod edge;
 pub mod node;
 
-use crate::{instruction::ToolList, AmbiguitySet, GrabToolType, ToolState};
+use crate::{
+    instruction::ToolList,
+    keymap::{Action, Keymap},
+    AmbiguitySet, GrabToolType, ToolState,
+};
 use bevy::prelude::*;
 
 use self::{
@@ -79,6 +83,6 @@ impl Plugin for WorkspaceDragDropPlugin {
     }
 }
 
-fn setup(mut tool_list: ResMut<ToolList>) {
-    tool_list.insert("G: Move selected".to_string());
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Grab));
 }
@@ -73,7 +73,7 @@ pub(crate) fn grab_node_setup(
     }
 }
 
-fn update_node_gui_edges(world: &mut World, node_id: NodeId) {
+pub(crate) fn update_node_gui_edges(world: &mut World, node_id: NodeId) {
     let node_transform = *world
         .query::<(&NodeIdComponent, &Transform)>()
         .iter(world)
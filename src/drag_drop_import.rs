@@ -1,15 +1,17 @@
-use std::sync::{Arc, RwLock};
+/// Importing images by dropping them on the window from a file manager.
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
 
 use bevy::prelude::*;
 use vismut_core::{live_graph::LiveGraph, node::node_type::NodeType};
 
 use crate::{
-    add_tool::create_and_grab_node,
-    camera::Cursor,
+    add_tool::{create_node_at, select_new_nodes, IMAGE_EXTENSIONS, MULTI_IMPORT_SPACING},
     instruction::ToolList,
-    mouse_interaction::Selected,
-    sync_graph::NODE_SIZE,
-    undo::{prelude::UndoCommandManager, UndoCommand},
+    undo::prelude::*,
+    workspace::Workspace,
     AmbiguitySet, CustomStage, ToolState,
 };
 
@@ -17,7 +19,7 @@ pub(crate) struct DragDropImport;
 
 impl Plugin for DragDropImport {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup.system())
+        app.add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
             .add_system_set_to_stage(
                 CoreStage::Update,
                 SystemSet::new().label(CustomStage::Input).with_system(
@@ -34,18 +36,43 @@ fn setup(mut tool_list: ResMut<ToolList>) {
     tool_list.insert("Drag and drop to import image".to_string());
 }
 
+fn is_image(path: &Path) -> bool {
+    path.extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+        .map_or(false, |extension| {
+            IMAGE_EXTENSIONS.contains(&extension.as_str())
+        })
+}
+
+/// Creates an `Image` node for each dropped image, at the drop position. Several images are
+/// stacked downwards like when importing several images with the add tool.
 fn drag_drop_import(
     mut undo_command_manager: ResMut<UndoCommandManager>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    workspace: Res<Workspace>,
     mut events: EventReader<FileDragAndDrop>,
 ) {
     let mut created_nodes: usize = 0;
 
     for event in events.iter() {
         if let FileDragAndDrop::DroppedFile { id: _, path_buf } = event {
+            if !is_image(path_buf) {
+                warn!("Not an image, skipping: {:?}", path_buf);
+                continue;
+            }
+
             let node_type = NodeType::Image(path_buf.clone());
+            let translation = workspace.cursor_world
+                - Vec2::new(0.0, MULTI_IMPORT_SPACING * created_nodes as f32);
 
-            if create_and_grab_node(&mut undo_command_manager, &*live_graph, &node_type).is_ok() {
+            if create_node_at(
+                &mut undo_command_manager,
+                &*live_graph,
+                &node_type,
+                translation,
+            )
+            .is_ok()
+            {
                 created_nodes += 1;
             } else {
                 error!("failed to create node: {:?}", node_type);
@@ -53,7 +80,8 @@ fn drag_drop_import(
         }
     }
 
-    if created_nodes > 1 {
-        undo_command_manager.push(Box::new(MultiImportOffset));
+    if created_nodes > 0 {
+        select_new_nodes(&mut undo_command_manager);
+        undo_command_manager.push(Box::new(Checkpoint));
     }
 }
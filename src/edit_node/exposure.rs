@@ -0,0 +1,81 @@
+use std::sync::{Arc, RwLock};
+
+use bevy::prelude::*;
+use vismut_core::live_graph::LiveGraph;
+
+use crate::{
+    instruction::*,
+    node_registry::Parameter,
+    scan_code_input::ScanCodeInput,
+    text_input::{TextField, TextInputEvent, Validation},
+    thumbnail::{SetThumbnailExposure, ThumbnailExposures},
+    undo::prelude::*,
+};
+
+use super::{has, push_batch, start_typing, EditState, EditedNodes};
+
+pub(super) fn edit_exposure_enter(
+    mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
+    mut text_field: ResMut<TextField>,
+    edited_nodes: Res<EditedNodes>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    exposures: Res<ThumbnailExposures>,
+) {
+    if let Ok(mut instructions) = q_instructions.get_single_mut() {
+        let values = edited_nodes
+            .nodes(&live_graph.read().unwrap())
+            .into_iter()
+            .filter(|node| has(node, Parameter::Exposure))
+            .map(|node| exposures.exposure(node.node_id).to_string());
+
+        start_typing(
+            &mut instructions,
+            &mut text_field,
+            "Thumbnail exposure",
+            values,
+            Validation::Float,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn edit_exposure_update(
+    mut char_input_events: EventReader<ReceivedCharacter>,
+    mut scan_code_input: ResMut<ScanCodeInput>,
+    mut text_field: ResMut<TextField>,
+    mut edit_state: ResMut<State<EditState>>,
+    edited_nodes: Res<EditedNodes>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    exposures: Res<ThumbnailExposures>,
+    mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+) {
+    if let (Ok(mut instructions), Some(input)) =
+        (q_instructions.get_single_mut(), text_field.0.as_mut())
+    {
+        match input.update(&mut char_input_events, &mut scan_code_input) {
+            Some(TextInputEvent::Submit(text)) => {
+                if let Ok(exposure) = text.parse::<f32>() {
+                    let commands = edited_nodes
+                        .nodes(&live_graph.read().unwrap())
+                        .iter()
+                        .filter(|node| has(node, Parameter::Exposure))
+                        .map(|node| {
+                            Box::new(SetThumbnailExposure {
+                                node_id: node.node_id,
+                                from: exposures.exposure(node.node_id),
+                                to: exposure,
+                            }) as BoxUndoCommand
+                        })
+                        .collect();
+                    push_batch(&mut undo_command_manager, commands);
+                }
+                edit_state.overwrite_replace(EditState::Outer).unwrap();
+            }
+            Some(TextInputEvent::Cancel) => {
+                edit_state.overwrite_replace(EditState::Outer).unwrap();
+            }
+            None => instructions.sections[1].value = input.display(),
+        }
+    }
+}
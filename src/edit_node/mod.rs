@@ -1,29 +1,50 @@
+mod exposure;
 mod name;
+mod normal_map;
 mod size;
 mod slot;
 mod value;
 
-use std::sync::{Arc, RwLock};
+use std::{
+    fmt::Debug,
+    sync::{Arc, RwLock},
+};
 
 use bevy::prelude::*;
 use vismut_core::{
     live_graph::LiveGraph,
     node::{mix::MixType, node_type::NodeType, Node, ResizeFilter, ResizePolicy},
+    node_graph::NodeId,
 };
 
 use crate::{
     core_translation::Translator,
+    embed::{embed_image_node, extract_dialog, extract_embedded_node, EmbeddedImages},
+    export_format::{ExportFormat, ExportFormats, SetExportFormat},
     instruction::*,
+    keymap::{Action, Context, Keymap},
     listable::*,
-    mouse_interaction::active::Active,
+    mouse_interaction::{active::Active, select::Selected},
+    node_error::NodeError,
+    node_registry::{self, Parameter},
+    normal_map::{GreenChannel, NormalMap, NormalMaps, SetNormalMap},
+    notification::Notifications,
     scan_code_input::*,
     shared::NodeIdComponent,
-    undo::{gui::GuiUndoCommand, prelude::*},
+    text_input::{TextField, TextInput, Validation},
+    thumbnail::ThumbnailExposures,
+    undo::{
+        gui::GuiUndoCommand,
+        node::{AddInput, RemoveInput},
+        prelude::*,
+    },
     AmbiguitySet, CustomStage, ToolState,
 };
 
 use self::{
+    exposure::{edit_exposure_enter, edit_exposure_update},
     name::{edit_name_enter, edit_name_update},
+    normal_map::{edit_strength_enter, edit_strength_update},
     size::{edit_specific_size_enter, edit_specific_size_update},
     slot::{edit_specific_slot_enter, edit_specific_slot_update},
     value::{edit_value_enter, edit_value_update},
@@ -38,6 +59,8 @@ enum EditState {
     Slot,
     Value,
     Name,
+    Exposure,
+    Strength,
 }
 
 #[derive(Clone, Debug)]
@@ -45,16 +68,45 @@ enum EditTarget {
     ResizePolicy,
     ResizeFilter,
     MixType,
+    ExportFormat,
+    GreenChannel,
 }
 
 type OptionEditTarget = Option<EditTarget>;
 
+/// The nodes the edit tool changes: every selected node, with the active node first. The first
+/// node is the one that is shown, and the one that gets the edits that only make sense for a
+/// single node, like renaming.
+#[derive(Debug, Default)]
+pub(crate) struct EditedNodes(Vec<NodeId>);
+
+impl std::ops::Deref for EditedNodes {
+    type Target = Vec<NodeId>;
+    fn deref(&self) -> &Vec<NodeId> {
+        &self.0
+    }
+}
+
+impl EditedNodes {
+    /// The nodes in the `LiveGraph`, the first one first.
+    pub fn nodes(&self, live_graph: &LiveGraph) -> Vec<Node> {
+        self.iter()
+            .filter_map(|node_id| live_graph.node(*node_id).ok())
+            .collect()
+    }
+}
+
+/// Shown when the edited nodes have different values.
+const MIXED: &str = "mixed";
+
 pub(crate) struct EditNodePlugin;
 
 impl Plugin for EditNodePlugin {
     fn build(&self, app: &mut App) {
         app.add_state(EditState::None)
             .insert_resource(OptionEditTarget::default())
+            .insert_resource(EditedNodes::default())
+            .insert_resource(EmbeddedImages::default())
             .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
             .add_system_set_to_stage(
                 CoreStage::Update,
@@ -125,89 +177,265 @@ impl Plugin for EditNodePlugin {
                         edit_name_update
                             .system()
                             .with_run_criteria(State::on_update(EditState::Name)),
+                    )
+                    .with_system(
+                        edit_exposure_enter
+                            .system()
+                            .with_run_criteria(State::on_enter(EditState::Exposure)),
+                    )
+                    .with_system(
+                        edit_exposure_update
+                            .system()
+                            .with_run_criteria(State::on_update(EditState::Exposure)),
+                    )
+                    .with_system(
+                        edit_strength_enter
+                            .system()
+                            .with_run_criteria(State::on_enter(EditState::Strength)),
+                    )
+                    .with_system(
+                        edit_strength_update
+                            .system()
+                            .with_run_criteria(State::on_update(EditState::Strength)),
                     ),
             );
     }
 }
 
-fn setup(mut tool_list: ResMut<ToolList>) {
-    tool_list.insert("Tab: Edit active".to_string());
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::EditNode));
 }
 
+/// Applies the commands as one undo step.
+pub(super) fn push_batch(
+    undo_command_manager: &mut UndoCommandManager,
+    commands: Vec<BoxUndoCommand>,
+) {
+    if !commands.is_empty() {
+        undo_command_manager.push(Box::new(commands));
+        undo_command_manager.push(Box::new(Checkpoint));
+    }
+}
+
+/// Commands that set a value on each node that `to` gives a value for.
+pub(super) fn set_each<T>(
+    live_graph: &LiveGraph,
+    nodes: &[Node],
+    mut to: impl FnMut(&Node) -> Option<T>,
+) -> Vec<BoxUndoCommand>
+where
+    T: Debug + Clone + Send + Sync + 'static,
+    NodeId: Translator<T>,
+{
+    nodes
+        .iter()
+        .filter_map(|node| {
+            let to = to(node)?;
+            let from = node.node_id.get(live_graph).ok()?;
+            Some(Box::new(GuiUndoCommand::new(node.node_id, from, to)) as BoxUndoCommand)
+        })
+        .collect()
+}
+
+pub(super) fn has(node: &Node, parameter: Parameter) -> bool {
+    node_registry::info(&node.node_type).has(parameter)
+}
+
+/// The value that all the nodes have, or `None` if they don't all have the same value.
+fn common(mut values: impl Iterator<Item = String>) -> Option<String> {
+    let first = values.next().unwrap_or_default();
+    if values.all(|value| value == first) {
+        Some(first)
+    } else {
+        None
+    }
+}
+
+/// The value to show for several nodes, or "mixed" if they don't all have the same value.
+fn shown(values: impl Iterator<Item = String>) -> String {
+    common(values).unwrap_or_else(|| MIXED.to_string())
+}
+
+/// Starts typing a value for the edited nodes. The field starts with the value they all have, or
+/// empty with "mixed" in the prompt if their values differ.
+pub(super) fn start_typing(
+    instructions: &mut Text,
+    text_field: &mut TextField,
+    prompt: &str,
+    values: impl Iterator<Item = String>,
+    validation: Validation,
+) {
+    let (current, prompt) = match common(values) {
+        Some(current) => (current, format!("{}: ", prompt)),
+        None => (String::new(), format!("{} ({}): ", prompt, MIXED)),
+    };
+    let input = TextInput::new(current, validation);
+
+    instructions.sections[0].value = prompt;
+    instructions.sections[1].value = input.display();
+    **text_field = Some(input);
+}
+
+fn node_error<'a>(
+    q_node_error: &'a Query<(&NodeIdComponent, &NodeError)>,
+    node_id: NodeId,
+) -> Option<&'a NodeError> {
+    q_node_error
+        .iter()
+        .find(|(node_id_component, _)| node_id_component.0 == node_id)
+        .map(|(_, node_error)| node_error)
+}
+
+#[allow(clippy::too_many_arguments)]
 fn tool_update(
     mut edit_state: ResMut<State<EditState>>,
     mut tool_state: ResMut<State<ToolState>>,
     mut scan_code_input: ResMut<ScanCodeInput>,
     mut instructions: ResMut<Instructions>,
     mut edit_target: ResMut<OptionEditTarget>,
-    q_active: Query<&NodeIdComponent, With<Active>>,
+    edited_nodes: Res<EditedNodes>,
+    q_node_error: Query<(&NodeIdComponent, &NodeError)>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    keymap: Res<Keymap>,
+    export_formats: Res<ExportFormats>,
+    exposures: Res<ThumbnailExposures>,
+    normal_maps: Res<NormalMaps>,
+    mut embedded_images: ResMut<EmbeddedImages>,
+    mut notifications: ResMut<Notifications>,
 ) {
-    let active_id = if let Ok(node_id) = q_active.get_single() {
-        if let Ok(node) = live_graph.read().unwrap().node(node_id.0) {
-            show_instructions(&node, &mut instructions);
-        } else {
+    let nodes = edited_nodes.nodes(&live_graph.read().unwrap());
+    let (active_id, node_type) = match nodes.first() {
+        Some(node) => (node.node_id, node.node_type.clone()),
+        None => {
             tool_state.overwrite_replace(ToolState::None).unwrap();
             return;
         }
-        node_id.0
-    } else {
-        tool_state.overwrite_replace(ToolState::None).unwrap();
-        return;
-    };
-
-    let node_type = if let Ok(node) = live_graph.read().unwrap().node(active_id) {
-        node.node_type
-    } else {
-        tool_state.overwrite_replace(ToolState::None).unwrap();
-        return;
     };
+    show_instructions(
+        &nodes,
+        node_error(&q_node_error, active_id),
+        &export_formats,
+        &exposures,
+        &normal_maps,
+        &keymap,
+        &mut instructions,
+    );
 
     let scan_codes: Vec<ScanCode> = scan_code_input.get_just_pressed().copied().collect();
 
     for scan_code in scan_codes {
-        if match scan_code {
-            ScanCode::KeyN => {
-                if let NodeType::OutputRgba(_) = node_type {
-                    edit_state.overwrite_replace(EditState::Name).unwrap();
-                    true
-                } else {
-                    false
-                }
+        let action = match keymap.action(Context::Edit, scan_code, &scan_code_input) {
+            Some(action) => action,
+            None => continue,
+        };
+
+        if let Some(parameter) = edited_parameter(action) {
+            let applies = if first_node_only(parameter) {
+                has(&nodes[0], parameter)
+            } else {
+                nodes.iter().any(|node| has(node, parameter))
+            };
+            if !applies {
+                continue;
             }
-            ScanCode::Tab => {
+        }
+
+        if match action {
+            Action::EditName => {
+                edit_state.overwrite_replace(EditState::Name).unwrap();
+                true
+            }
+            Action::EditExit => {
                 tool_state.overwrite_replace(ToolState::None).unwrap();
                 true
             }
-            ScanCode::KeyR => {
+            Action::EditResizePolicy => {
                 instructions.insert(InstructId::Tool, ResizePolicy::list());
                 *edit_target = Some(EditTarget::ResizePolicy);
                 edit_state.overwrite_replace(EditState::Inner).unwrap();
                 true
             }
-            ScanCode::KeyF => {
+            Action::EditResizeFilter => {
                 instructions.insert(InstructId::Tool, ResizeFilter::list());
                 *edit_target = Some(EditTarget::ResizeFilter);
                 edit_state.overwrite_replace(EditState::Inner).unwrap();
                 true
             }
-            ScanCode::KeyT => {
-                if let NodeType::Mix(_) = node_type {
-                    instructions.insert(InstructId::Tool, MixType::list());
-                    *edit_target = Some(EditTarget::MixType);
-                    edit_state.overwrite_replace(EditState::Inner).unwrap();
-                    true
-                } else {
-                    false
+            Action::EditMixType => {
+                instructions.insert(InstructId::Tool, MixType::list());
+                *edit_target = Some(EditTarget::MixType);
+                edit_state.overwrite_replace(EditState::Inner).unwrap();
+                true
+            }
+            Action::EditExportFormat => {
+                instructions.insert(InstructId::Tool, ExportFormat::list());
+                *edit_target = Some(EditTarget::ExportFormat);
+                edit_state.overwrite_replace(EditState::Inner).unwrap();
+                true
+            }
+            Action::EditGreenChannel => {
+                instructions.insert(InstructId::Tool, GreenChannel::list());
+                *edit_target = Some(EditTarget::GreenChannel);
+                edit_state.overwrite_replace(EditState::Inner).unwrap();
+                true
+            }
+            Action::EditEmbed => {
+                match embed_image_node(&*live_graph, &mut *embedded_images, active_id) {
+                    Ok(to) => {
+                        undo_command_manager.push(Box::new(GuiUndoCommand::new(
+                            active_id,
+                            node_type.clone(),
+                            to,
+                        )));
+                        undo_command_manager.push(Box::new(Checkpoint));
+                    }
+                    Err(e) => notifications.error(format!("Unable to embed the image: {}", e)),
                 }
+                true
             }
-            ScanCode::KeyV => {
-                if let NodeType::Value(_) = node_type {
-                    edit_state.overwrite_replace(EditState::Value).unwrap();
-                    true
-                } else {
-                    false
+            Action::EditExposure => {
+                edit_state.overwrite_replace(EditState::Exposure).unwrap();
+                true
+            }
+            Action::EditExtract => {
+                if let Some(path) = extract_dialog(&mut *scan_code_input) {
+                    match extract_embedded_node(&*live_graph, &*embedded_images, active_id, &path) {
+                        Ok(to) => {
+                            undo_command_manager.push(Box::new(GuiUndoCommand::new(
+                                active_id,
+                                node_type.clone(),
+                                to,
+                            )));
+                            undo_command_manager.push(Box::new(Checkpoint));
+                        }
+                        Err(e) => {
+                            notifications.error(format!("Unable to extract the image: {}", e))
+                        }
+                    }
                 }
+                true
+            }
+            Action::EditValue => {
+                edit_state.overwrite_replace(EditState::Value).unwrap();
+                true
+            }
+            Action::EditStrength => {
+                edit_state.overwrite_replace(EditState::Strength).unwrap();
+                true
+            }
+            Action::EditAddInput | Action::EditRemoveInput => {
+                // These make their own commands when they are applied, which the checkpoint turns
+                // into one undo step.
+                for node in nodes.iter().filter(|node| has(node, Parameter::InputCount)) {
+                    if action == Action::EditAddInput {
+                        undo_command_manager.push(Box::new(AddInput(node.node_id)));
+                    } else {
+                        undo_command_manager.push(Box::new(RemoveInput(node.node_id)));
+                    }
+                }
+                undo_command_manager.push(Box::new(Checkpoint));
+                true
             }
             _ => false,
         } {
@@ -217,24 +445,28 @@ fn tool_update(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn edit(
     mut edit_state: ResMut<State<EditState>>,
     mut scan_code_input: ResMut<ScanCodeInput>,
     mut edit_target: ResMut<OptionEditTarget>,
-    q_active: Query<&NodeIdComponent, With<Active>>,
+    edited_nodes: Res<EditedNodes>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
     mut undo_command_manager: ResMut<UndoCommandManager>,
+    keymap: Res<Keymap>,
+    export_formats: Res<ExportFormats>,
+    normal_maps: Res<NormalMaps>,
 ) {
     let mut done = false;
 
-    if let (Some(edit_target), Ok(node_id)) = (&*edit_target, q_active.get_single()) {
+    if let Some(edit_target) = &*edit_target {
         if let Ok(live_graph) = live_graph.read() {
+            let nodes = edited_nodes.nodes(&live_graph);
             let scan_codes: Vec<ScanCode> = scan_code_input.get_just_pressed().copied().collect();
-            let mut parameter_set = false;
 
             for scan_code in scan_codes {
                 if let Some(i) = scan_code.to_usize() {
-                    match edit_target {
+                    let commands = match edit_target {
                         EditTarget::ResizePolicy => match ResizePolicy::choose(i) {
                             Some(ResizePolicy::SpecificSize(_)) => {
                                 edit_state.overwrite_replace(EditState::Size).unwrap();
@@ -244,49 +476,55 @@ fn edit(
                                 edit_state.overwrite_replace(EditState::Slot).unwrap();
                                 return;
                             }
-                            Some(to) => {
-                                if let Ok(from) = node_id.0.get(&*live_graph) {
-                                    undo_command_manager
-                                        .push(Box::new(GuiUndoCommand::new(node_id.0, from, to)));
-                                    undo_command_manager.push(Box::new(Checkpoint));
-                                    parameter_set = true;
-                                }
-                            }
-                            None => (),
+                            Some(to) => Some(set_each(&live_graph, &nodes, |_| Some(to))),
+                            None => None,
                         },
-                        EditTarget::ResizeFilter => {
-                            if let (Ok(from), Some(to)) =
-                                (node_id.0.get(&*live_graph), ResizeFilter::choose(i))
-                            {
-                                undo_command_manager
-                                    .push(Box::new(GuiUndoCommand::new(node_id.0, from, to)));
-                                undo_command_manager.push(Box::new(Checkpoint));
-                                parameter_set = true;
-                            }
-                        }
-                        EditTarget::MixType => {
-                            if let Some(mix_type) = MixType::choose(i) {
-                                if let Ok(from) = node_id.0.get(&*live_graph) {
-                                    undo_command_manager.push(Box::new(GuiUndoCommand::new(
-                                        node_id.0,
+                        EditTarget::ResizeFilter => ResizeFilter::choose(i)
+                            .map(|to| set_each(&live_graph, &nodes, |_| Some(to))),
+                        EditTarget::MixType => MixType::choose(i).map(|mix_type| {
+                            set_each(&live_graph, &nodes, |node| match node.node_type {
+                                NodeType::Mix(_) => Some(NodeType::Mix(mix_type)),
+                                _ => None,
+                            })
+                        }),
+                        EditTarget::ExportFormat => ExportFormat::choose(i).map(|to| {
+                            nodes
+                                .iter()
+                                .filter(|node| has(node, Parameter::ExportFormat))
+                                .map(|node| {
+                                    Box::new(SetExportFormat {
+                                        node_id: node.node_id,
+                                        from: export_formats.format(node.node_id),
+                                        to,
+                                    }) as BoxUndoCommand
+                                })
+                                .collect()
+                        }),
+                        EditTarget::GreenChannel => GreenChannel::choose(i).map(|green| {
+                            nodes
+                                .iter()
+                                .filter(|node| has(node, Parameter::GreenChannel))
+                                .map(|node| {
+                                    let from = normal_maps.normal_map(node.node_id);
+                                    Box::new(SetNormalMap {
+                                        node_id: node.node_id,
                                         from,
-                                        NodeType::Mix(mix_type),
-                                    )));
-                                    undo_command_manager.push(Box::new(Checkpoint));
-                                    parameter_set = true;
-                                } else {
-                                    error!("unable to get node with id: {}", node_id.0);
-                                }
-                            }
-                        }
-                    }
+                                        to: NormalMap { green, ..from },
+                                    }) as BoxUndoCommand
+                                })
+                                .collect()
+                        }),
+                    };
 
-                    if parameter_set {
+                    if let Some(commands) = commands {
+                        push_batch(&mut undo_command_manager, commands);
                         scan_code_input.clear_just_pressed(scan_code);
                         done = true;
                         break;
                     }
-                } else if scan_code == ScanCode::Tab {
+                } else if keymap.action(Context::Edit, scan_code, &scan_code_input)
+                    == Some(Action::EditExit)
+                {
                     scan_code_input.clear_just_pressed(scan_code);
                     done = true;
                     break;
@@ -301,90 +539,247 @@ fn edit(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn edit_exit(
-    q_active: Query<&NodeIdComponent, With<Active>>,
+    mut text_field: ResMut<TextField>,
+    edited_nodes: Res<EditedNodes>,
+    q_node_error: Query<(&NodeIdComponent, &NodeError)>,
     mut instructions: ResMut<Instructions>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    keymap: Res<Keymap>,
+    export_formats: Res<ExportFormats>,
+    exposures: Res<ThumbnailExposures>,
+    normal_maps: Res<NormalMaps>,
 ) {
-    if let Ok(node_id) = q_active.get_single() {
-        if let Ok(node) = live_graph.read().unwrap().node(node_id.0) {
-            show_instructions(&node, &mut instructions);
-        } else {
-            error!("Could not find a node with that ID in the graph");
-        }
+    **text_field = None;
+
+    let nodes = edited_nodes.nodes(&live_graph.read().unwrap());
+    if let Some(node) = nodes.first() {
+        show_instructions(
+            &nodes,
+            node_error(&q_node_error, node.node_id),
+            &export_formats,
+            &exposures,
+            &normal_maps,
+            &keymap,
+            &mut instructions,
+        );
+    } else {
+        error!("Could not find a node with that ID in the graph");
     }
 }
 
-fn show_instructions(node: &Node, instructions: &mut Instructions) {
+/// Shows the parameters of the edited nodes. `nodes` can't be empty.
+fn show_instructions(
+    nodes: &[Node],
+    node_error: Option<&NodeError>,
+    export_formats: &ExportFormats,
+    exposures: &ThumbnailExposures,
+    normal_maps: &NormalMaps,
+    keymap: &Keymap,
+    instructions: &mut Instructions,
+) {
+    let node = &nodes[0];
     let generic_instructions = format!(
-        "R: Resize policy ({})\nF: Resize filter ({})",
-        node.resize_policy, node.resize_filter
+        "{} ({})\n{} ({})",
+        keymap.help(Action::EditResizePolicy),
+        shown(nodes.iter().map(|node| node.resize_policy.to_string())),
+        keymap.help(Action::EditResizeFilter),
+        shown(nodes.iter().map(|node| node.resize_filter.to_string())),
     );
 
-    let specific_instructions = {
-        if let Some(name) = node.node_type.name() {
-            format!("N: Name: {}", name)
-        } else {
-            match &node.node_type {
-                NodeType::Image(path) => format!("Path: {:#?}", path),
-                NodeType::Mix(mix_type) => format!("T: Type: {}", mix_type),
-                NodeType::Value(value) => format!("V: Value: {}", value),
-                NodeType::CombineRgba | NodeType::SeparateRgba => String::new(),
-                _ => "Unsupported node".to_string(),
-            }
+    // The parameters of the active node come first, followed by the ones only the other nodes
+    // have, the same ones `tool_update` accepts.
+    let info = node_registry::info(&node.node_type);
+    let mut parameters = info.parameters.to_vec();
+    for parameter in nodes[1..]
+        .iter()
+        .flat_map(|node| node_registry::info(&node.node_type).parameters)
+    {
+        if !first_node_only(*parameter) && !parameters.contains(parameter) {
+            parameters.push(*parameter);
         }
+    }
+
+    let specific_instructions = parameters
+        .iter()
+        .map(|parameter| {
+            parameter_instructions(
+                *parameter,
+                nodes,
+                export_formats,
+                exposures,
+                normal_maps,
+                keymap,
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let title = match nodes.len() {
+        1 => info.name.to_string(),
+        2 => format!("{} and 1 other node", info.name),
+        count => format!("{} and {} other nodes", info.name, count - 1),
+    };
+
+    let error = match node_error {
+        Some(node_error) => format!("\n\nError: {}", node_error.0),
+        None => String::new(),
     };
 
     instructions.insert(
         InstructId::Tool,
         format!(
-            "# {}\n{}\n\n{}",
-            node_type_name(&node.node_type),
-            specific_instructions,
-            generic_instructions
+            "# {}\n{}\n{}\n\n{}{}",
+            title, info.help, specific_instructions, generic_instructions, error
         ),
     );
 }
 
+#[allow(clippy::too_many_arguments)]
 fn tool_enter(
     mut edit_state: ResMut<State<EditState>>,
     mut tool_state: ResMut<State<ToolState>>,
-    q_active: Query<&NodeIdComponent, With<Active>>,
+    mut edited_nodes: ResMut<EditedNodes>,
+    q_selected: Query<(&NodeIdComponent, Option<&Active>), With<Selected>>,
+    q_node_error: Query<(&NodeIdComponent, &NodeError)>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
     mut instructions: ResMut<Instructions>,
+    keymap: Res<Keymap>,
+    export_formats: Res<ExportFormats>,
+    exposures: Res<ThumbnailExposures>,
+    normal_maps: Res<NormalMaps>,
 ) {
-    if let Ok(node_id) = q_active.get_single() {
-        if let Ok(node) = live_graph.read().unwrap().node(node_id.0) {
-            let _ = edit_state.overwrite_replace(EditState::Outer);
+    let mut selected: Vec<(NodeId, bool)> = q_selected
+        .iter()
+        .map(|(node_id, active)| (node_id.0, active.is_some()))
+        .collect();
+    // The sort is stable, so this only moves the active node to the front.
+    selected.sort_by_key(|(_, active)| !active);
+    *edited_nodes = EditedNodes(selected.into_iter().map(|(node_id, _)| node_id).collect());
 
-            show_instructions(&node, &mut instructions);
-        } else {
-            error!("Could not find a node with that ID in the graph");
-            tool_state.overwrite_replace(ToolState::None).unwrap();
-        }
+    let nodes = edited_nodes.nodes(&live_graph.read().unwrap());
+    if let Some(node) = nodes.first() {
+        let _ = edit_state.overwrite_replace(EditState::Outer);
+
+        show_instructions(
+            &nodes,
+            node_error(&q_node_error, node.node_id),
+            &export_formats,
+            &exposures,
+            &normal_maps,
+            &keymap,
+            &mut instructions,
+        );
     } else {
         tool_state.overwrite_replace(ToolState::None).unwrap();
     }
 }
 
-fn tool_exit(mut edit_state: ResMut<State<EditState>>) {
+fn tool_exit(
+    mut edit_state: ResMut<State<EditState>>,
+    mut text_field: ResMut<TextField>,
+    mut edited_nodes: ResMut<EditedNodes>,
+) {
     let _ = edit_state.overwrite_replace(EditState::None);
+    **text_field = None;
+    edited_nodes.0.clear();
 }
 
-fn node_type_name(node_type: &NodeType) -> &'static str {
-    match node_type {
-        NodeType::InputGray(_) => "InputGray",
-        NodeType::InputRgba(_) => "InputRgba",
-        NodeType::OutputGray(_) => "OutputGray",
-        NodeType::OutputRgba(_) => "OutputRgba",
-        NodeType::Graph(_) => "Graph",
-        NodeType::Image(_) => "Image",
-        NodeType::Embed(_) => "Embedded Image",
-        NodeType::Write(_) => "Write",
-        NodeType::Value(_) => "Value",
-        NodeType::Mix(_) => "Mix",
-        NodeType::HeightToNormal => "Height To Normal",
-        NodeType::SeparateRgba => "Separate RGBA",
-        NodeType::CombineRgba => "Combine RGBA",
+/// The parameter an action edits, for the actions that only work on some node types.
+fn edited_parameter(action: Action) -> Option<Parameter> {
+    match action {
+        Action::EditName => Some(Parameter::Name),
+        Action::EditExportFormat => Some(Parameter::ExportFormat),
+        Action::EditMixType => Some(Parameter::MixType),
+        Action::EditAddInput | Action::EditRemoveInput => Some(Parameter::InputCount),
+        Action::EditValue => Some(Parameter::Value),
+        Action::EditExposure => Some(Parameter::Exposure),
+        Action::EditEmbed => Some(Parameter::Embed),
+        Action::EditExtract => Some(Parameter::Extract),
+        Action::EditStrength => Some(Parameter::Strength),
+        Action::EditGreenChannel => Some(Parameter::GreenChannel),
+        _ => None,
+    }
+}
+
+/// Parameters that only belong to the first edited node, since editing them on several nodes at
+/// once doesn't make sense. Output names have to be unique, extracting asks for a file, and only
+/// one path is shown.
+fn first_node_only(parameter: Parameter) -> bool {
+    matches!(
+        parameter,
+        Parameter::Name | Parameter::Path | Parameter::Embed | Parameter::Extract
+    )
+}
+
+fn parameter_instructions(
+    parameter: Parameter,
+    nodes: &[Node],
+    export_formats: &ExportFormats,
+    exposures: &ThumbnailExposures,
+    normal_maps: &NormalMaps,
+    keymap: &Keymap,
+) -> String {
+    let node = &nodes[0];
+    let with_parameter = || nodes.iter().filter(move |node| has(node, parameter));
+
+    match (parameter, &node.node_type) {
+        (Parameter::Name, NodeType::OutputRgba(name) | NodeType::OutputGray(name)) => {
+            format!("{}: {}", keymap.help(Action::EditName), name)
+        }
+        (Parameter::ExportFormat, _) => format!(
+            "{}: {}",
+            keymap.help(Action::EditExportFormat),
+            shown(with_parameter().map(|node| export_formats.format(node.node_id).to_string()))
+        ),
+        (Parameter::MixType, _) => format!(
+            "{}: {}",
+            keymap.help(Action::EditMixType),
+            shown(with_parameter().filter_map(|node| match &node.node_type {
+                NodeType::Mix(mix_type) => Some(mix_type.to_string()),
+                _ => None,
+            }))
+        ),
+        (Parameter::InputCount, _) => format!(
+            "{}\n{}",
+            keymap.help(Action::EditAddInput),
+            keymap.help(Action::EditRemoveInput)
+        ),
+        (Parameter::Value, _) => format!(
+            "{}: {}",
+            keymap.help(Action::EditValue),
+            shown(with_parameter().filter_map(|node| match &node.node_type {
+                NodeType::Value(value) => Some(value.to_string()),
+                _ => None,
+            }))
+        ),
+        (Parameter::Path, NodeType::Image(path)) => format!("Path: {:#?}", path),
+        (Parameter::Exposure, _) => format!(
+            "{}: {}",
+            keymap.help(Action::EditExposure),
+            shown(with_parameter().map(|node| exposures.exposure(node.node_id).to_string()))
+        ),
+        (Parameter::Embed, _) => keymap.help(Action::EditEmbed),
+        (Parameter::Extract, _) => keymap.help(Action::EditExtract),
+        (Parameter::Strength, _) => format!(
+            "{}: {}",
+            keymap.help(Action::EditStrength),
+            shown(
+                with_parameter()
+                    .map(|node| { normal_maps.normal_map(node.node_id).strength.to_string() })
+            )
+        ),
+        (Parameter::GreenChannel, _) => format!(
+            "{}: {}",
+            keymap.help(Action::EditGreenChannel),
+            shown(
+                with_parameter().map(|node| normal_maps.normal_map(node.node_id).green.to_string())
+            )
+        ),
+        (parameter, node_type) => {
+            error!("{:?} is not a parameter of {:?}", parameter, node_type);
+            String::new()
+        }
     }
 }
@@ -6,70 +6,70 @@ use vismut_core::{live_graph::LiveGraph, node::node_type::NodeType};
 use crate::{
     core_translation::Translator,
     instruction::*,
-    mouse_interaction::active::Active,
-    shared::NodeIdComponent,
+    scan_code_input::ScanCodeInput,
+    text_input::{TextField, TextInput, TextInputEvent, Validation},
     undo::{gui::GuiUndoCommand, prelude::*},
 };
 
-use super::EditState;
+use super::{EditState, EditedNodes};
 
+#[allow(clippy::too_many_arguments)]
 pub(super) fn edit_name_update(
     mut char_input_events: EventReader<ReceivedCharacter>,
+    mut scan_code_input: ResMut<ScanCodeInput>,
+    mut text_field: ResMut<TextField>,
     mut edit_state: ResMut<State<EditState>>,
-    q_active: Query<&NodeIdComponent, With<Active>>,
+    edited_nodes: Res<EditedNodes>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
     mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
-    mut started: Local<bool>,
     mut undo_command_manager: ResMut<UndoCommandManager>,
 ) {
-    // This guard drops any input the first time the system is entered, so you do not get the
-    // input from the button that was pressed to start this sytem, in this sytem.
-    if !*started {
-        *started = true;
-        return;
-    }
-
-    if let (Ok(mut instructions), Ok(node_id)) =
-        (q_instructions.get_single_mut(), q_active.get_single())
-    {
-        for event in char_input_events.iter() {
-            if event.char == '\u{8}' {
-                // Backspace
-                instructions.sections[1].value.pop();
-            } else if event.char == '\r' {
-                // Enter
-                if let Ok(from) = node_id.0.get(&*live_graph.read().unwrap()) {
-                    let name = instructions.sections[1].value.clone();
-                    undo_command_manager.push(Box::new(GuiUndoCommand::new(node_id.0, from, name)));
+    if let (Ok(mut instructions), Some(node_id), Some(input)) = (
+        q_instructions.get_single_mut(),
+        edited_nodes.first().copied(),
+        text_field.0.as_mut(),
+    ) {
+        match input.update(&mut char_input_events, &mut scan_code_input) {
+            Some(TextInputEvent::Submit(name)) => {
+                if name.is_empty() {
+                    input.set_error("The name can't be empty");
+                    instructions.sections[1].value = input.display();
+                    return;
+                }
+                if let Ok(from) = node_id.get(&*live_graph.read().unwrap()) {
+                    undo_command_manager.push(Box::new(GuiUndoCommand::new(node_id, from, name)));
                     undo_command_manager.push(Box::new(Checkpoint));
-                } else {
-                    warn!("Invalid name");
                 }
                 edit_state.overwrite_replace(EditState::Outer).unwrap();
-                *started = false;
-            } else {
-                instructions.sections[1].value.push(event.char);
             }
+            Some(TextInputEvent::Cancel) => {
+                edit_state.overwrite_replace(EditState::Outer).unwrap();
+            }
+            None => instructions.sections[1].value = input.display(),
         }
     }
 }
 
 pub(super) fn edit_name_enter(
     mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
-    q_active: Query<&NodeIdComponent, With<Active>>,
+    mut text_field: ResMut<TextField>,
+    edited_nodes: Res<EditedNodes>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
 ) {
-    if let (Ok(node_id), Ok(mut instructions)) =
-        (q_active.get_single(), q_instructions.get_single_mut())
-    {
-        if let Ok(node) = live_graph.read().unwrap().node(node_id.0) {
-            if let NodeType::OutputRgba(name) = node.node_type {
-                instructions.sections[0].value = "Name: ".into();
-                instructions.sections[1].value = name;
-            } else {
-                instructions.sections[0].value = "Name: ".to_string();
-                instructions.sections[1].value.clear();
-            }
+    if let (Some(node_id), Ok(mut instructions)) = (
+        edited_nodes.first().copied(),
+        q_instructions.get_single_mut(),
+    ) {
+        if let Ok(node) = live_graph.read().unwrap().node(node_id) {
+            let name = match node.node_type {
+                NodeType::OutputRgba(name) | NodeType::OutputGray(name) => name,
+                _ => String::new(),
+            };
+            let input = TextInput::new(name, Validation::Text);
+
+            instructions.sections[0].value = "Name: ".to_string();
+            instructions.sections[1].value = input.display();
+            **text_field = Some(input);
         }
     }
 }
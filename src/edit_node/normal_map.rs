@@ -0,0 +1,82 @@
+use std::sync::{Arc, RwLock};
+
+use bevy::prelude::*;
+use vismut_core::live_graph::LiveGraph;
+
+use crate::{
+    instruction::*,
+    node_registry::Parameter,
+    normal_map::{NormalMap, NormalMaps, SetNormalMap},
+    scan_code_input::ScanCodeInput,
+    text_input::{TextField, TextInputEvent, Validation},
+    undo::prelude::*,
+};
+
+use super::{has, push_batch, start_typing, EditState, EditedNodes};
+
+pub(super) fn edit_strength_enter(
+    mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
+    mut text_field: ResMut<TextField>,
+    edited_nodes: Res<EditedNodes>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    normal_maps: Res<NormalMaps>,
+) {
+    if let Ok(mut instructions) = q_instructions.get_single_mut() {
+        let values = edited_nodes
+            .nodes(&live_graph.read().unwrap())
+            .into_iter()
+            .filter(|node| has(node, Parameter::Strength))
+            .map(|node| normal_maps.normal_map(node.node_id).strength.to_string());
+
+        start_typing(
+            &mut instructions,
+            &mut text_field,
+            "Strength",
+            values,
+            Validation::Float,
+        );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(super) fn edit_strength_update(
+    mut char_input_events: EventReader<ReceivedCharacter>,
+    mut scan_code_input: ResMut<ScanCodeInput>,
+    mut text_field: ResMut<TextField>,
+    mut edit_state: ResMut<State<EditState>>,
+    edited_nodes: Res<EditedNodes>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    normal_maps: Res<NormalMaps>,
+    mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+) {
+    if let (Ok(mut instructions), Some(input)) =
+        (q_instructions.get_single_mut(), text_field.0.as_mut())
+    {
+        match input.update(&mut char_input_events, &mut scan_code_input) {
+            Some(TextInputEvent::Submit(text)) => {
+                if let Ok(strength) = text.parse::<f32>() {
+                    let commands = edited_nodes
+                        .nodes(&live_graph.read().unwrap())
+                        .iter()
+                        .filter(|node| has(node, Parameter::Strength))
+                        .map(|node| {
+                            let from = normal_maps.normal_map(node.node_id);
+                            Box::new(SetNormalMap {
+                                node_id: node.node_id,
+                                from,
+                                to: NormalMap { strength, ..from },
+                            }) as BoxUndoCommand
+                        })
+                        .collect();
+                    push_batch(&mut undo_command_manager, commands);
+                }
+                edit_state.overwrite_replace(EditState::Outer).unwrap();
+            }
+            Some(TextInputEvent::Cancel) => {
+                edit_state.overwrite_replace(EditState::Outer).unwrap();
+            }
+            None => instructions.sections[1].value = input.display(),
+        }
+    }
+}
@@ -4,104 +4,94 @@ use bevy::prelude::*;
 use vismut_core::{live_graph::LiveGraph, node::ResizePolicy};
 
 use crate::{
-    core_translation::Translator,
     instruction::*,
-    mouse_interaction::active::Active,
-    shared::NodeIdComponent,
-    undo::{gui::GuiUndoCommand, prelude::*},
+    notification::Notifications,
+    scan_code_input::ScanCodeInput,
+    text_input::{TextField, TextInputEvent, Validation},
+    undo::prelude::*,
 };
 
-use super::EditState;
+use super::{push_batch, set_each, start_typing, EditState, EditedNodes};
 
 pub(super) fn edit_specific_slot_enter(
     mut edit_state: ResMut<State<EditState>>,
     mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
-    q_active: Query<&NodeIdComponent, With<Active>>,
+    mut text_field: ResMut<TextField>,
+    mut notifications: ResMut<Notifications>,
+    edited_nodes: Res<EditedNodes>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
 ) {
-    if let (Ok(node_id), Ok(mut instructions)) =
-        (q_active.get_single(), q_instructions.get_single_mut())
-    {
-        if let Ok(node) = live_graph.read().unwrap().node(node_id.0) {
-            if node.input_slots().is_empty() {
-                warn!("The node doesn't have any input slots");
-                edit_state.overwrite_set(EditState::Outer).unwrap();
-                return;
-            } else if let ResizePolicy::SpecificSlot(slot) = node.resize_policy {
-                instructions.sections[0].value = format!("Current: {}\nNew: ", slot);
-            } else {
-                instructions.sections[0].value = format!(
-                    "Available slots are 0 through {}\nChoice: ",
-                    node.input_slots().len()
-                );
-            }
+    if let Ok(mut instructions) = q_instructions.get_single_mut() {
+        let nodes = edited_nodes.nodes(&live_graph.read().unwrap());
+        let slot_count = nodes
+            .iter()
+            .map(|node| node.input_slots().len())
+            .max()
+            .unwrap_or_default();
+        if slot_count == 0 {
+            notifications.error("The node doesn't have any input slots");
+            edit_state.overwrite_set(EditState::Outer).unwrap();
+            return;
         }
-        instructions.sections[1].value.clear();
+
+        let indices = nodes.iter().map(|node| match node.resize_policy {
+            ResizePolicy::SpecificSlot(slot_id) => node
+                .input_slots()
+                .iter()
+                .position(|slot| slot.slot_id == slot_id)
+                .map(|index| index.to_string())
+                .unwrap_or_default(),
+            _ => String::new(),
+        });
+
+        start_typing(
+            &mut instructions,
+            &mut text_field,
+            &format!("Slot, 0 through {}", slot_count - 1),
+            indices,
+            Validation::Integer,
+        );
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 pub(super) fn edit_specific_slot_update(
     mut char_input_events: EventReader<ReceivedCharacter>,
+    mut scan_code_input: ResMut<ScanCodeInput>,
+    mut text_field: ResMut<TextField>,
     mut edit_state: ResMut<State<EditState>>,
-    q_active: Query<&NodeIdComponent, With<Active>>,
+    edited_nodes: Res<EditedNodes>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
     mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
-    mut started: Local<bool>,
     mut undo_command_manager: ResMut<UndoCommandManager>,
 ) {
-    // This guard drops any input the first time the system is entered, so you do not get the
-    // input from the button that was pressed to start this sytem, in this sytem.
-    if !*started {
-        *started = true;
-        return;
-    }
-
-    if let (Ok(mut instructions), Ok(node_id)) =
-        (q_instructions.get_single_mut(), q_active.get_single())
+    if let (Ok(mut instructions), Some(input)) =
+        (q_instructions.get_single_mut(), text_field.0.as_mut())
     {
-        for event in char_input_events.iter() {
-            if event.char.is_digit(10) {
-                instructions.sections[1].value.push(event.char);
-            } else if event.char == '\u{8}' {
-                // Backspace
-                instructions.sections[1].value.pop();
-            } else if event.char == '\r' {
-                // Enter
-                if let Ok(index) = instructions.sections[1].value.parse::<u32>() {
-                    if let Ok(node) = live_graph.read().unwrap().node(node_id.0) {
-                        if let (Ok(from), Some(slot)) = (
-                            node_id.0.get(&*live_graph.read().unwrap()),
-                            node.input_slots().get(index as usize),
-                        ) {
-                            let slot_id = (*slot).slot_id;
-                            if node.input_slot_with_id(slot_id).is_ok() {
-                                undo_command_manager.push(Box::new(GuiUndoCommand::new(
-                                    node_id.0,
-                                    from,
-                                    ResizePolicy::SpecificSlot(slot_id),
-                                )));
-                                undo_command_manager.push(Box::new(Checkpoint));
-                            } else {
-                                warn!("Node does not have a slot with the given ID: {}", slot_id);
-                            }
-                        } else {
-                            warn!("That slot does not exist: {}", index);
-                        }
-                    } else {
-                        error!(
-                            "The node you're trying to edit does not exist: {}",
-                            node_id.0
-                        );
-                    }
+        match input.update(&mut char_input_events, &mut scan_code_input) {
+            Some(TextInputEvent::Submit(text)) => {
+                let index = text.parse::<usize>().ok();
+                let live_graph = live_graph.read().unwrap();
+                let nodes = edited_nodes.nodes(&live_graph);
+
+                // Nodes without that slot keep their resize policy.
+                let commands = set_each(&live_graph, &nodes, |node| {
+                    let slot = node.input_slots().get(index?)?.slot_id;
+                    Some(ResizePolicy::SpecificSlot(slot))
+                });
+
+                if commands.is_empty() {
+                    input.set_error(format!("There is no slot {}", text));
+                    instructions.sections[1].value = input.display();
                 } else {
-                    error!(
-                        "Could not parse the input as a number: {}",
-                        instructions.sections[1].value
-                    );
+                    push_batch(&mut undo_command_manager, commands);
+                    edit_state.overwrite_replace(EditState::Outer).unwrap();
                 }
+            }
+            Some(TextInputEvent::Cancel) => {
                 edit_state.overwrite_replace(EditState::Outer).unwrap();
-                *started = false;
             }
+            None => instructions.sections[1].value = input.display(),
         }
     }
 }
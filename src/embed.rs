@@ -0,0 +1,205 @@
+/// Embedding the pixels of `Image` nodes in the project, so projects can be shared without their
+/// image files, and extracting them back out to files.
+///
+/// Embedded images are stored in the project file as base64 encoded 16-bit PNGs, or as 32-bit
+/// float OpenEXRs if they have values outside of 0 to 1, like HDR images.
+use std::{
+    collections::HashMap,
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::*;
+use image::ImageOutputFormat;
+use native_dialog::FileDialog;
+use serde::{Deserialize, Serialize};
+use vismut_core::{
+    live_graph::{LiveGraph, NodeState},
+    node::{embed::EmbeddedSlotDataId, node_type::NodeType},
+    node_graph::{NodeId, SlotId},
+    slot_data::{Buffer, SlotData, SlotImage},
+};
+
+use crate::{
+    export_format::{slot_pixels, ExportFormat, Pixels},
+    scan_code_input::ScanCodeInput,
+};
+
+/// The format images are embedded as, unless they need more range.
+const EMBED_FORMAT: ExportFormat = ExportFormat::Png16;
+const EMBED_FORMAT_HDR: ExportFormat = ExportFormat::Exr32;
+
+/// The format that keeps all values of `pixels`.
+fn embed_format(pixels: &Pixels) -> ExportFormat {
+    let values = match pixels {
+        Pixels::Gray(values) | Pixels::Rgb(values) | Pixels::Rgba(values) => values,
+    };
+
+    if values.iter().all(|value| (0.0..=1.0).contains(value)) {
+        EMBED_FORMAT
+    } else {
+        EMBED_FORMAT_HDR
+    }
+}
+
+/// The pixels of the embedded images in the `LiveGraph`, kept so they can be saved with the
+/// project.
+#[derive(Debug, Default)]
+pub(crate) struct EmbeddedImages(pub HashMap<EmbeddedSlotDataId, Arc<SlotData>>);
+
+impl std::ops::Deref for EmbeddedImages {
+    type Target = HashMap<EmbeddedSlotDataId, Arc<SlotData>>;
+    fn deref(&self) -> &HashMap<EmbeddedSlotDataId, Arc<SlotData>> {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for EmbeddedImages {
+    fn deref_mut(&mut self) -> &mut HashMap<EmbeddedSlotDataId, Arc<SlotData>> {
+        &mut self.0
+    }
+}
+
+/// An embedded image as it's stored in a project file.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct EmbeddedImage {
+    format: ExportFormat,
+    data: String,
+}
+
+impl EmbeddedImage {
+    pub(crate) fn encode(slot_data: &SlotData) -> Result<Self> {
+        let (size, pixels) = slot_pixels(&slot_data.image);
+        let format = embed_format(&pixels);
+
+        let mut bytes = Vec::new();
+        format.image(size, pixels)?.write_to(
+            &mut Cursor::new(&mut bytes),
+            ImageOutputFormat::from(format.image_format()),
+        )?;
+
+        Ok(Self {
+            format,
+            data: base64::encode(bytes),
+        })
+    }
+
+    pub(crate) fn decode(&self) -> Result<SlotData> {
+        let image = image::load_from_memory_with_format(
+            &base64::decode(&self.data)?,
+            self.format.image_format(),
+        )?
+        .into_rgba32f();
+        let (width, height) = image.dimensions();
+
+        let mut buffers = (0..4)
+            .map(|channel| {
+                let raw = image.pixels().map(|pixel| pixel[channel]).collect();
+                Buffer::from_raw(width, height, raw)
+                    .map(Box::new)
+                    .ok_or_else(|| anyhow!("the embedded image is corrupt"))
+            })
+            .collect::<Result<Vec<Box<Buffer>>>>()?;
+
+        Ok(SlotData::new(
+            NodeId(0),
+            SlotId(0),
+            SlotImage::from_buffers_rgba(&mut buffers)?,
+        ))
+    }
+
+    /// Adds the image to the `LiveGraph`, and returns the node type of a node that uses it.
+    pub(crate) fn embed(
+        &self,
+        live_graph: &mut LiveGraph,
+        embedded_images: &mut EmbeddedImages,
+    ) -> Result<NodeType> {
+        let slot_data = Arc::new(self.decode()?);
+        let embedded_slot_data_id = live_graph.embed_slot_data(Arc::clone(&slot_data))?;
+        embedded_images.insert(embedded_slot_data_id, slot_data);
+
+        Ok(NodeType::Embed(embedded_slot_data_id))
+    }
+}
+
+/// Embeds the current pixels of an `Image` node, and returns the node type to change the node to.
+pub(crate) fn embed_image_node(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    embedded_images: &mut EmbeddedImages,
+    node_id: NodeId,
+) -> Result<NodeType> {
+    let mut live_graph = live_graph.write().map_err(|e| anyhow!("{}", e))?;
+
+    if !matches!(live_graph.node(node_id)?.node_type, NodeType::Image(_)) {
+        bail!("only image nodes can be embedded");
+    }
+    if live_graph.node_state(node_id)? != NodeState::Clean {
+        bail!("the image has not been loaded yet");
+    }
+
+    let slot_data = Arc::clone(live_graph.slot_data(node_id, SlotId(0))?);
+    let embedded_slot_data_id = live_graph.embed_slot_data(Arc::clone(&slot_data))?;
+    embedded_images.insert(embedded_slot_data_id, slot_data);
+
+    Ok(NodeType::Embed(embedded_slot_data_id))
+}
+
+/// Writes the pixels of an `Embed` node to `path`, and returns the node type to change the node
+/// to.
+pub(crate) fn extract_embedded_node(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    embedded_images: &EmbeddedImages,
+    node_id: NodeId,
+    path: &Path,
+) -> Result<NodeType> {
+    let embedded_slot_data_id = match live_graph
+        .read()
+        .map_err(|e| anyhow!("{}", e))?
+        .node(node_id)?
+        .node_type
+    {
+        NodeType::Embed(embedded_slot_data_id) => embedded_slot_data_id,
+        _ => bail!("only embedded images can be extracted"),
+    };
+    let slot_data = embedded_images
+        .get(&embedded_slot_data_id)
+        .ok_or_else(|| anyhow!("the embedded image is missing"))?;
+
+    let format = match path.extension().and_then(|extension| extension.to_str()) {
+        Some(extension) if extension.eq_ignore_ascii_case(EMBED_FORMAT_HDR.extension()) => {
+            EMBED_FORMAT_HDR
+        }
+        _ => EMBED_FORMAT,
+    };
+
+    let (size, pixels) = slot_pixels(&slot_data.image);
+    format.save(path, size, pixels)?;
+
+    Ok(NodeType::Image(path.to_path_buf()))
+}
+
+/// Asks where to extract an embedded image to. The image is written as an OpenEXR if the path
+/// ends in `.exr`, which keeps values outside of 0 to 1, and as a 16-bit PNG otherwise.
+pub(crate) fn extract_dialog(scan_code_input: &mut ScanCodeInput) -> Option<PathBuf> {
+    scan_code_input.reset_all();
+
+    match FileDialog::new()
+        .add_filter("PNG image", &[EMBED_FORMAT.extension()])
+        .add_filter("OpenEXR image", &[EMBED_FORMAT_HDR.extension()])
+        .show_save_single_file()
+    {
+        Ok(Some(mut path)) => {
+            if path.extension().is_none() {
+                path.set_extension(EMBED_FORMAT.extension());
+            }
+            Some(path)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Unable to get the path to extract to: {:?}\n", e);
+            None
+        }
+    }
+}
@@ -1,12 +1,14 @@
 use crate::{
-    instruction::ToolList, shared::NodeIdComponent, AmbiguitySet, CustomStage, Selected, ToolState,
+    export_format::{pixels, ExportFormats},
+    instruction::ToolList,
+    keymap::{Action, Keymap},
+    notification::Notifications,
+    shared::NodeIdComponent,
+    AmbiguitySet, CustomStage, Selected, ToolState,
 };
 use bevy::prelude::*;
 use native_dialog::FileDialog;
-use std::{
-    path::Path,
-    sync::{Arc, RwLock},
-};
+use std::sync::{Arc, RwLock};
 use vismut_core::{
     error::TexProError, live_graph::LiveGraph, node_graph::SlotId, slot_data::Size as TPSize,
 };
@@ -31,14 +33,16 @@ impl Plugin for ExportPlugin {
     }
 }
 
-fn setup(mut tool_list: ResMut<ToolList>) {
-    tool_list.insert("Shift Alt E: Export active".to_string());
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Export));
 }
 
 fn export(
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    export_formats: Res<ExportFormats>,
     q_selected: Query<&NodeIdComponent, With<Selected>>,
     mut tool_state: ResMut<State<ToolState>>,
+    mut notifications: ResMut<Notifications>,
 ) {
     for node_id in q_selected.iter() {
         let _result = LiveGraph::await_clean_read(&live_graph, node_id.0);
@@ -53,14 +57,15 @@ fn export(
                 continue;
             }
             Err(e) => {
-                error!("Unable to get the size of the node: {}", e);
+                notifications.error(format!("Unable to get the size of the node: {}", e));
                 continue;
             }
         };
 
+        let export_format = export_formats.format(node_id.0);
         let path = match FileDialog::new()
             // .set_location("~/Desktop")
-            .add_filter("PNG Image", &["png"])
+            .add_filter(&export_format.to_string(), &[export_format.extension()])
             .show_save_single_file()
         {
             Ok(path) => path,
@@ -70,40 +75,27 @@ fn export(
             }
         };
 
-        let path = match path {
+        let mut path = match path {
             Some(path) => path,
             None => {
                 warn!("Invalid export path");
                 continue;
             }
         };
+        path.set_extension(export_format.extension());
 
-        let texels = match live_graph.read().unwrap().buffer_rgba(node_id.0, SlotId(0)) {
-            Ok(buf) => buf,
+        let pixels = match pixels(&live_graph.read().unwrap(), node_id.0) {
+            Ok(pixels) => pixels,
             Err(e) => {
-                error!("Error when trying to get pixels from image: {:?}", e);
-                continue;
-            }
-        };
-
-        let buffer = match image::RgbaImage::from_vec(size.width, size.height, texels) {
-            None => {
-                error!("Output image buffer not big enough to contain texels.");
+                notifications.error(format!("Unable to get the pixels of the node: {}", e));
                 continue;
             }
-            Some(buf) => buf,
         };
 
-        match image::save_buffer(
-            &Path::new(&path),
-            &buffer,
-            size.width,
-            size.height,
-            image::ColorType::RGBA(8),
-        ) {
+        match export_format.save(&path, size, pixels) {
             Ok(_) => info!("Image exported to {:?}", path),
             Err(e) => {
-                error!("{}", e);
+                notifications.error(format!("Unable to export {:?}: {}", path, e));
                 continue;
             }
         }
@@ -0,0 +1,269 @@
+/// The file formats and bit depths that nodes can be exported as.
+use std::{collections::HashMap, fmt, path::Path};
+
+use anyhow::{anyhow, Result};
+use bevy::prelude::*;
+use image::{DynamicImage, ImageBuffer, ImageFormat, Luma, Rgb, Rgba};
+use serde::{Deserialize, Serialize};
+use vismut_core::{
+    live_graph::LiveGraph,
+    node_graph::{NodeId, SlotId},
+    slot_data::{ChannelPixel, Size as CoreSize, SlotImage},
+};
+
+use crate::{listable::Listable, undo::prelude::*};
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum ExportFormat {
+    Png8,
+    Png16,
+    Tga8,
+    Tiff8,
+    Tiff16,
+    Exr32,
+}
+
+impl Default for ExportFormat {
+    fn default() -> Self {
+        Self::Png8
+    }
+}
+
+impl fmt::Display for ExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Png8 => "PNG 8-bit",
+            Self::Png16 => "PNG 16-bit",
+            Self::Tga8 => "TGA 8-bit",
+            Self::Tiff8 => "TIFF 8-bit",
+            Self::Tiff16 => "TIFF 16-bit",
+            Self::Exr32 => "OpenEXR 32-bit float",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Listable<Self> for ExportFormat {
+    fn list() -> String {
+        let mut output = "## Export format\n".to_string();
+        let entries = vec![
+            Self::Png8.to_string(),
+            Self::Png16.to_string(),
+            Self::Tga8.to_string(),
+            Self::Tiff8.to_string(),
+            Self::Tiff16.to_string(),
+            Self::Exr32.to_string(),
+        ];
+        for (i, entry) in entries.iter().enumerate() {
+            output = format!("{}{}: {}\n", output, i + 1, entry);
+        }
+        output
+    }
+
+    fn choose(i: usize) -> Option<Self> {
+        const MAX_CHOICE: usize = 6;
+
+        if i <= MAX_CHOICE {
+            Some(match i {
+                1 => Self::Png8,
+                2 => Self::Png16,
+                3 => Self::Tga8,
+                4 => Self::Tiff8,
+                5 => Self::Tiff16,
+                _ => Self::Exr32,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+impl ExportFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            Self::Png8 | Self::Png16 => "png",
+            Self::Tga8 => "tga",
+            Self::Tiff8 | Self::Tiff16 => "tiff",
+            Self::Exr32 => "exr",
+        }
+    }
+
+    pub(crate) fn image_format(self) -> ImageFormat {
+        match self {
+            Self::Png8 | Self::Png16 => ImageFormat::Png,
+            Self::Tga8 => ImageFormat::Tga,
+            Self::Tiff8 | Self::Tiff16 => ImageFormat::Tiff,
+            Self::Exr32 => ImageFormat::OpenExr,
+        }
+    }
+
+    /// Writes `pixels` to `path`, converting them to this format's bit depth.
+    pub(crate) fn save(self, path: &Path, size: CoreSize, pixels: Pixels) -> Result<()> {
+        self.image(size, pixels)?
+            .save_with_format(path, self.image_format())?;
+        Ok(())
+    }
+
+    /// Converts `pixels` to an image with this format's bit depth. Grayscale pixels become a
+    /// single channel, except in OpenEXR where they become RGB.
+    pub(crate) fn image(self, size: CoreSize, pixels: Pixels) -> Result<DynamicImage> {
+        let too_small = || anyhow!("output image buffer not big enough to contain texels");
+        let (width, height) = (size.width, size.height);
+
+        let image = match (self, pixels) {
+            (Self::Png8 | Self::Tga8 | Self::Tiff8, Pixels::Gray(pixels)) => {
+                DynamicImage::ImageLuma8(
+                    ImageBuffer::<Luma<u8>, _>::from_raw(width, height, to_u8(pixels))
+                        .ok_or_else(too_small)?,
+                )
+            }
+            (Self::Png8 | Self::Tga8 | Self::Tiff8, Pixels::Rgba(pixels)) => {
+                DynamicImage::ImageRgba8(
+                    ImageBuffer::<Rgba<u8>, _>::from_raw(width, height, to_u8(pixels))
+                        .ok_or_else(too_small)?,
+                )
+            }
+            (Self::Png8 | Self::Tga8 | Self::Tiff8, Pixels::Rgb(pixels)) => {
+                DynamicImage::ImageRgb8(
+                    ImageBuffer::<Rgb<u8>, _>::from_raw(width, height, to_u8(pixels))
+                        .ok_or_else(too_small)?,
+                )
+            }
+            (Self::Png16 | Self::Tiff16, Pixels::Gray(pixels)) => DynamicImage::ImageLuma16(
+                ImageBuffer::<Luma<u16>, _>::from_raw(width, height, to_u16(pixels))
+                    .ok_or_else(too_small)?,
+            ),
+            (Self::Png16 | Self::Tiff16, Pixels::Rgba(pixels)) => DynamicImage::ImageRgba16(
+                ImageBuffer::<Rgba<u16>, _>::from_raw(width, height, to_u16(pixels))
+                    .ok_or_else(too_small)?,
+            ),
+            (Self::Png16 | Self::Tiff16, Pixels::Rgb(pixels)) => DynamicImage::ImageRgb16(
+                ImageBuffer::<Rgb<u16>, _>::from_raw(width, height, to_u16(pixels))
+                    .ok_or_else(too_small)?,
+            ),
+            (Self::Exr32, Pixels::Gray(pixels)) => DynamicImage::ImageRgb32F(
+                ImageBuffer::<Rgb<f32>, _>::from_raw(
+                    width,
+                    height,
+                    pixels.into_iter().flat_map(|pixel| [pixel; 3]).collect(),
+                )
+                .ok_or_else(too_small)?,
+            ),
+            (Self::Exr32, Pixels::Rgb(pixels)) => DynamicImage::ImageRgb32F(
+                ImageBuffer::<Rgb<f32>, _>::from_raw(width, height, pixels)
+                    .ok_or_else(too_small)?,
+            ),
+            (Self::Exr32, Pixels::Rgba(pixels)) => DynamicImage::ImageRgba32F(
+                ImageBuffer::<Rgba<f32>, _>::from_raw(width, height, pixels)
+                    .ok_or_else(too_small)?,
+            ),
+        };
+
+        Ok(image)
+    }
+}
+
+fn to_u8(pixels: Vec<ChannelPixel>) -> Vec<u8> {
+    pixels
+        .into_iter()
+        .map(|pixel| (pixel.clamp(0.0, 1.0) * u8::MAX as f32).round() as u8)
+        .collect()
+}
+
+fn to_u16(pixels: Vec<ChannelPixel>) -> Vec<u16> {
+    pixels
+        .into_iter()
+        .map(|pixel| (pixel.clamp(0.0, 1.0) * u16::MAX as f32).round() as u16)
+        .collect()
+}
+
+/// The full precision pixels of a slot.
+pub(crate) enum Pixels {
+    Gray(Vec<ChannelPixel>),
+    /// Interleaved RGB.
+    Rgb(Vec<ChannelPixel>),
+    /// Interleaved RGBA.
+    Rgba(Vec<ChannelPixel>),
+}
+
+/// Reads the first output slot of a node at full precision.
+pub(crate) fn pixels(live_graph: &LiveGraph, node_id: NodeId) -> Result<Pixels> {
+    let slot_data = live_graph.slot_data(node_id, SlotId(0))?;
+    Ok(slot_pixels(&slot_data.image).1)
+}
+
+/// Reads a `SlotImage` at full precision, along with its size.
+pub(crate) fn slot_pixels(image: &SlotImage) -> (CoreSize, Pixels) {
+    let (width, height) = match image {
+        SlotImage::Gray(buf) => buf.transient_buffer().buffer().dimensions(),
+        SlotImage::Rgba(bufs) => bufs[0].transient_buffer().buffer().dimensions(),
+    };
+
+    let pixels = match image {
+        SlotImage::Gray(buf) => Pixels::Gray(buf.transient_buffer().buffer().as_raw().clone()),
+        SlotImage::Rgba(bufs) => {
+            let channels: Vec<Vec<ChannelPixel>> = bufs
+                .iter()
+                .map(|buf| buf.transient_buffer().buffer().as_raw().clone())
+                .collect();
+
+            let pixel_count = channels[0].len();
+            Pixels::Rgba(
+                (0..pixel_count)
+                    .flat_map(|i| channels.iter().map(move |channel| channel[i]))
+                    .collect(),
+            )
+        }
+    };
+
+    (CoreSize::new(width, height), pixels)
+}
+
+/// The export format of output nodes. Nodes that are not in here are exported as
+/// `ExportFormat::default()`.
+///
+/// Like `InputCounts`, this is kept outside of the GUI nodes so a node gets its format back when
+/// its removal is undone.
+#[derive(Debug, Default)]
+pub(crate) struct ExportFormats(pub HashMap<NodeId, ExportFormat>);
+
+impl std::ops::Deref for ExportFormats {
+    type Target = HashMap<NodeId, ExportFormat>;
+    fn deref(&self) -> &HashMap<NodeId, ExportFormat> {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for ExportFormats {
+    fn deref_mut(&mut self) -> &mut HashMap<NodeId, ExportFormat> {
+        &mut self.0
+    }
+}
+
+impl ExportFormats {
+    pub fn format(&self, node_id: NodeId) -> ExportFormat {
+        self.get(&node_id).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SetExportFormat {
+    pub node_id: NodeId,
+    pub from: ExportFormat,
+    pub to: ExportFormat,
+}
+impl UndoCommand for SetExportFormat {
+    fn forward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        set_export_format(world, self.node_id, self.to);
+    }
+
+    fn backward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        set_export_format(world, self.node_id, self.from);
+    }
+}
+
+fn set_export_format(world: &mut World, node_id: NodeId, export_format: ExportFormat) {
+    if let Some(mut export_formats) = world.get_resource_mut::<ExportFormats>() {
+        export_formats.insert(node_id, export_format);
+    }
+}
@@ -1,18 +1,36 @@
 use std::{
-    path::PathBuf,
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
     sync::{Arc, RwLock},
 };
 
+use anyhow::{anyhow, bail, Result};
 use bevy::prelude::*;
 use native_dialog::FileDialog;
 use vismut_core::{
-    error::TexProError, live_graph::LiveGraph, node::node_type::NodeType, node_graph::SlotId,
+    edge::Edge,
+    error::TexProError,
+    live_graph::LiveGraph,
+    node::node_type::NodeType,
+    node_graph::{NodeId, SlotId},
     slot_data::Size as CoreSize,
 };
 
 use crate::{
+    export_format::{pixels, ExportFormat, ExportFormats, Pixels},
+    export_template::ExportTemplate,
+    group::GroupStack,
     instruction::{InstructId, Instructions, ToolList},
+    keymap::{Action, Keymap},
+    normal_map::NormalMaps,
+    notification::Notifications,
+    packing::{
+        pack_texture, packed_path, PackedTexture, PackingPreset, PackingPresets, SelectedPreset,
+    },
+    project::ProjectPath,
     scan_code_input::ScanCodeInput,
+    sync_graph::Edge as GuiEdge,
     AmbiguitySet, ToolState,
 };
 
@@ -24,6 +42,7 @@ pub(crate) struct ExportOutputsToolPlugin;
 impl Plugin for ExportOutputsToolPlugin {
     fn build(&self, app: &mut App) {
         app.insert_resource(ExportPath(None))
+            .insert_resource(ExportFormats::default())
             .insert_resource(WaitedFrame(0))
             .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
             .add_system_set_to_stage(
@@ -43,8 +62,9 @@ impl Plugin for ExportOutputsToolPlugin {
     }
 }
 
-fn setup(mut tool_list: ResMut<ToolList>) {
-    tool_list.insert("Ctrl (Shift) E: Export outputs".to_string());
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::ExportOutputs));
+    tool_list.insert(keymap.help(Action::ExportOutputsAs));
 }
 
 fn export_dialog(scan_code_input: &mut ScanCodeInput) -> Option<PathBuf> {
@@ -59,86 +79,294 @@ fn export_dialog(scan_code_input: &mut ScanCodeInput) -> Option<PathBuf> {
     }
 }
 
-fn do_export(directory: Option<PathBuf>, live_graph: &Arc<RwLock<LiveGraph>>) {
-    if let Some(path) = directory {
-        let output_ids = live_graph.read().unwrap().output_ids();
+/// How the files of exported outputs are named and encoded.
+pub(crate) struct ExportSettings<'a> {
+    pub formats: &'a ExportFormats,
+    pub template: &'a ExportTemplate,
+    /// Used for the `{project}` token in the template.
+    pub project: &'a str,
+    /// Textures to pack from grayscale outputs, in addition to the outputs themselves.
+    pub preset: Option<&'a PackingPreset>,
+    /// Applied to outputs connected straight to a Height to Normal node, through `edges`.
+    pub normal_maps: &'a NormalMaps,
+    pub edges: &'a [Edge],
+}
 
-        for node_id in output_ids {
-            let live_graph = LiveGraph::await_clean_read(live_graph, node_id).unwrap();
-            let mut path = path.clone();
+/// Writes the files of one export into a directory, and keeps them from overwriting each other.
+pub(crate) struct Exporter<'a> {
+    pub settings: &'a ExportSettings<'a>,
+    directory: &'a Path,
+    /// The files that will be written, and what writes them. The paths are lowercase, since
+    /// file names that only differ in case are the same file on Windows and macOS.
+    claimed: HashMap<String, String>,
+}
 
-            let size: CoreSize = match live_graph.slot_data_size(node_id, SlotId(0)) {
-                Ok(s) => s,
-                Err(TexProError::InvalidBufferCount) => {
-                    warn!("Seems the node doesn't have any outputs");
-                    continue;
-                }
-                Err(e) => {
-                    error!("Unable to get the size of the node: {}", e);
-                    continue;
-                }
-            };
+impl<'a> Exporter<'a> {
+    pub fn new(settings: &'a ExportSettings<'a>, directory: &'a Path) -> Self {
+        Self {
+            settings,
+            directory,
+            claimed: HashMap::new(),
+        }
+    }
 
-            let file_name = if let NodeType::OutputRgba(file_name) =
-                live_graph.node(node_id).unwrap().node_type
-            {
-                file_name
-            } else {
-                error!("could not get name of output node with ID: {}", node_id);
-                continue;
-            };
+    /// The path of a file named by the export template, with `name` as the `{output}`.
+    pub fn path(&self, name: &str, export_format: ExportFormat, size: CoreSize) -> Result<PathBuf> {
+        let settings = self.settings;
+        let mut path = self
+            .directory
+            .join(settings.template.path(settings.project, name, size)?);
+        if let Some(file_name) = path.file_name() {
+            let file_name = format!(
+                "{}.{}",
+                file_name.to_string_lossy(),
+                export_format.extension()
+            );
+            path.set_file_name(file_name);
+        }
 
-            path.push(file_name);
-            path.set_extension("png");
+        Ok(path)
+    }
 
-            let texels = match live_graph.buffer_rgba(node_id, SlotId(0)) {
-                Ok(buf) => buf,
-                Err(e) => {
-                    error!("Error when trying to get pixels from image: {:?}", e);
-                    continue;
-                }
-            };
+    /// Reserves `path` for what `label` describes, unless something else already has it.
+    fn claim(&mut self, path: &Path, label: &str) -> Result<()> {
+        let key = path.to_string_lossy().to_lowercase();
+        if let Some(other) = self.claimed.get(&key) {
+            bail!(
+                "{} and {} would both be written to {:?}, give them unique names",
+                other,
+                label,
+                path
+            );
+        }
 
-            let buffer = match image::RgbaImage::from_vec(size.width, size.height, texels) {
-                None => {
-                    error!("Output image buffer not big enough to contain texels.");
-                    continue;
-                }
-                Some(buf) => buf,
-            };
+        self.claimed.insert(key, label.to_string());
+        Ok(())
+    }
+
+    /// Writes `pixels` to `path`, creating its folders if needed.
+    pub fn write(
+        &self,
+        path: &Path,
+        export_format: ExportFormat,
+        size: CoreSize,
+        pixels: Pixels,
+    ) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+
+        export_format.save(path, size, pixels)
+    }
+}
+
+/// What a file in an export is made from.
+enum Export<'a> {
+    Output(NodeId),
+    Packed(&'a PackedTexture),
+}
+
+fn output_size(live_graph: &LiveGraph, node_id: NodeId) -> Result<CoreSize> {
+    match live_graph.slot_data_size(node_id, SlotId(0)) {
+        Ok(size) => Ok(size),
+        Err(TexProError::InvalidBufferCount) => {
+            bail!("seems the node doesn't have any outputs");
+        }
+        Err(e) => {
+            bail!("unable to get the size of the node: {}", e);
+        }
+    }
+}
+
+/// Waits for an output node to finish processing, and returns the path it's written to.
+fn output_path(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    exporter: &Exporter,
+    node_id: NodeId,
+) -> Result<PathBuf> {
+    let live_graph = LiveGraph::await_clean_read(live_graph, node_id)?;
+    let size = output_size(&live_graph, node_id)?;
+
+    let output_name = if let NodeType::OutputRgba(name) | NodeType::OutputGray(name) =
+        live_graph.node(node_id)?.node_type
+    {
+        name
+    } else {
+        bail!("could not get name of output node with ID: {}", node_id);
+    };
+
+    exporter.path(
+        &output_name,
+        exporter.settings.formats.format(node_id),
+        size,
+    )
+}
+
+/// Writes the result of an output node to `path`, in the node's export format.
+fn export_output(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    exporter: &Exporter,
+    node_id: NodeId,
+    path: &Path,
+) -> Result<()> {
+    let live_graph = LiveGraph::await_clean_read(live_graph, node_id)?;
+    let size = output_size(&live_graph, node_id)?;
+    let mut pixels = pixels(&live_graph, node_id)?;
+    let settings = exporter.settings;
+    settings
+        .normal_maps
+        .applied(&live_graph, node_id, settings.edges)
+        .apply(&mut pixels);
+
+    exporter.write(
+        path,
+        exporter.settings.formats.format(node_id),
+        size,
+        pixels,
+    )
+}
+
+/// Exports all output nodes, and the textures of the packing preset, into `directory`. Returns
+/// what was exported along with its result.
+///
+/// The paths of all files are worked out before any of them are written, so if two of them
+/// would overwrite each other nothing is written.
+pub(crate) fn export_outputs(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    settings: &ExportSettings,
+    directory: &Path,
+) -> Result<Vec<(String, Result<PathBuf>)>> {
+    let output_ids = live_graph
+        .read()
+        .map_err(|e| anyhow!("{}", e))?
+        .output_ids();
+
+    let mut exporter = Exporter::new(settings, directory);
+    let mut exports = Vec::new();
+
+    for node_id in output_ids {
+        let path = output_path(live_graph, &exporter, node_id);
+        exports.push((format!("output {}", node_id), Export::Output(node_id), path));
+    }
+
+    if let Some(preset) = settings.preset {
+        for texture in &preset.textures {
+            let path = packed_path(live_graph, &exporter, texture);
+            exports.push((
+                format!("packed texture {:?}", texture.name),
+                Export::Packed(texture),
+                path,
+            ));
+        }
+    }
+
+    for (label, _, path) in &exports {
+        if let Ok(path) = path {
+            exporter.claim(path, label)?;
+        }
+    }
+
+    Ok(exports
+        .into_iter()
+        .map(|(label, export, path)| {
+            let result = path.and_then(|path| {
+                match export {
+                    Export::Output(node_id) => export_output(live_graph, &exporter, node_id, &path),
+                    Export::Packed(texture) => pack_texture(live_graph, &exporter, texture, &path),
+                }?;
+                Ok(path)
+            });
+            (label, result)
+        })
+        .collect())
+}
 
-            match image::save_buffer(
-                &path,
-                &buffer,
-                size.width,
-                size.height,
-                image::ColorType::RGBA(8),
-            ) {
-                Ok(_) => info!("Image exported to {:?}", path),
-                Err(e) => {
-                    error!("{}", e);
-                    continue;
+fn do_export(
+    directory: Option<PathBuf>,
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    settings: &ExportSettings,
+    notifications: &mut Notifications,
+) {
+    if let Some(path) = directory {
+        match export_outputs(live_graph, settings, &path) {
+            Ok(results) => {
+                for (exported, result) in results {
+                    match result {
+                        Ok(path) => info!("Image exported to {:?}", path),
+                        Err(e) => {
+                            notifications.error(format!("Unable to export {}: {}", exported, e))
+                        }
+                    }
                 }
             }
+            Err(e) => notifications.error(format!("Unable to export outputs: {}", e)),
         }
     } else {
         info!("cancelled file dialog");
     }
 }
 
+/// The selected packing preset. If it can't be found the outputs are exported without it, and the
+/// user is told.
+fn selected_preset_or_notify<'a>(
+    packing_presets: &'a PackingPresets,
+    selected_preset: &SelectedPreset,
+    notifications: &mut Notifications,
+) -> Option<&'a PackingPreset> {
+    match packing_presets.selected(selected_preset) {
+        Ok(preset) => preset,
+        Err(e) => {
+            notifications.error(format!("Unable to pack textures: {}", e));
+            None
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
 fn export_as(
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    export_formats: Res<ExportFormats>,
+    export_template: Res<ExportTemplate>,
+    packing_presets: Res<PackingPresets>,
+    selected_preset: Res<SelectedPreset>,
+    project_path: Res<ProjectPath>,
     mut tool_state: ResMut<State<ToolState>>,
     mut sc_input: ResMut<ScanCodeInput>,
     mut instructions: ResMut<Instructions>,
     mut export_path: ResMut<ExportPath>,
     mut waited_frame: ResMut<WaitedFrame>,
+    mut notifications: ResMut<Notifications>,
+    group_stack: Res<GroupStack>,
+    normal_maps: Res<NormalMaps>,
+    q_edge: Query<&GuiEdge>,
 ) {
+    if !group_stack.is_empty() {
+        notifications.error("Leave the group to export the outputs of the project");
+        tool_state.overwrite_replace(ToolState::None).unwrap();
+        return;
+    }
+
     if waited_frame.0 > 1 {
         let directory = export_dialog(&mut *sc_input);
         export_path.0 = directory.clone();
 
-        do_export(directory, &*live_graph);
+        let edges = q_edge
+            .iter()
+            .map(|gui_edge| Edge::from(*gui_edge))
+            .collect::<Vec<Edge>>();
+        let settings = ExportSettings {
+            formats: &*export_formats,
+            template: &*export_template,
+            project: &project_path.name(),
+            preset: selected_preset_or_notify(
+                &packing_presets,
+                &selected_preset,
+                &mut notifications,
+            ),
+            normal_maps: &*normal_maps,
+            edges: &edges,
+        };
+        do_export(directory, &*live_graph, &settings, &mut *notifications);
 
         tool_state.overwrite_replace(ToolState::None).unwrap();
         waited_frame.0 = 0;
@@ -148,20 +376,57 @@ fn export_as(
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn export(
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    export_formats: Res<ExportFormats>,
+    export_template: Res<ExportTemplate>,
+    packing_presets: Res<PackingPresets>,
+    selected_preset: Res<SelectedPreset>,
+    project_path: Res<ProjectPath>,
     mut tool_state: ResMut<State<ToolState>>,
     mut instructions: ResMut<Instructions>,
     export_path: ResMut<ExportPath>,
     mut waited_frame: ResMut<WaitedFrame>,
+    mut notifications: ResMut<Notifications>,
+    group_stack: Res<GroupStack>,
+    normal_maps: Res<NormalMaps>,
+    q_edge: Query<&GuiEdge>,
 ) {
+    if !group_stack.is_empty() {
+        notifications.error("Leave the group to export the outputs of the project");
+        tool_state.overwrite_replace(ToolState::None).unwrap();
+        return;
+    }
+
     if waited_frame.0 > 1 {
         if export_path.0.is_none() {
             tool_state
                 .overwrite_replace(ToolState::ExportOutputs(true))
                 .unwrap();
         } else {
-            do_export(export_path.0.clone(), &*live_graph);
+            let edges = q_edge
+                .iter()
+                .map(|gui_edge| Edge::from(*gui_edge))
+                .collect::<Vec<Edge>>();
+            let settings = ExportSettings {
+                formats: &*export_formats,
+                template: &*export_template,
+                project: &project_path.name(),
+                preset: selected_preset_or_notify(
+                    &packing_presets,
+                    &selected_preset,
+                    &mut notifications,
+                ),
+                normal_maps: &*normal_maps,
+                edges: &edges,
+            };
+            do_export(
+                export_path.0.clone(),
+                &*live_graph,
+                &settings,
+                &mut *notifications,
+            );
             tool_state.overwrite_replace(ToolState::None).unwrap();
         }
         waited_frame.0 = 0;
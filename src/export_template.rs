@@ -0,0 +1,260 @@
+/// Naming exported files with a project-level template.
+///
+/// The template can contain the tokens `{project}`, `{output}`, `{width}`, `{height}` and
+/// `{date}`, and `/` to put files in subfolders. Output names can contain `/` too, to give an
+/// output its own subfolder.
+use std::{
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use vismut_core::slot_data::Size as CoreSize;
+
+use crate::{
+    instruction::*,
+    keymap::{Action, Keymap},
+    scan_code_input::ScanCodeInput,
+    text_input::{TextField, TextInput, TextInputEvent, Validation},
+    undo::prelude::*,
+    AmbiguitySet, CustomStage, ToolState,
+};
+
+pub(crate) const DEFAULT_TEMPLATE: &str = "{output}";
+/// Used for `{project}` when the project hasn't been saved.
+pub(crate) const UNTITLED_PROJECT: &str = "untitled";
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) struct ExportTemplate(pub String);
+
+impl Default for ExportTemplate {
+    fn default() -> Self {
+        Self(DEFAULT_TEMPLATE.to_string())
+    }
+}
+
+impl ExportTemplate {
+    /// Returns the path of an exported file relative to the export directory, without an
+    /// extension.
+    pub fn path(&self, project: &str, output: &str, size: CoreSize) -> Result<PathBuf> {
+        let path: PathBuf = expand(&self.0, project, output, size)?
+            .split('/')
+            .filter(|segment| !segment.is_empty())
+            .map(sanitize)
+            .collect();
+
+        if path.as_os_str().is_empty() {
+            bail!("the export name template gives an empty file name");
+        }
+
+        Ok(path)
+    }
+}
+
+/// Replaces the tokens in `template` with their values.
+pub(crate) fn expand(
+    template: &str,
+    project: &str,
+    output: &str,
+    size: CoreSize,
+) -> Result<String> {
+    let mut expanded = String::new();
+    let mut chars = template.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '{' {
+            expanded.push(c);
+            continue;
+        }
+
+        let mut token = String::new();
+        let mut closed = false;
+        for c in chars.by_ref() {
+            if c == '}' {
+                closed = true;
+                break;
+            }
+            token.push(c);
+        }
+        if !closed {
+            bail!("unclosed {{ in {:?}", template);
+        }
+
+        match token.as_str() {
+            "project" => expanded.push_str(project),
+            "output" => expanded.push_str(output),
+            "width" => expanded.push_str(&size.width.to_string()),
+            "height" => expanded.push_str(&size.height.to_string()),
+            "date" => expanded.push_str(&date_today()),
+            _ => bail!("unknown token {{{}}} in {:?}", token, template),
+        }
+    }
+
+    Ok(expanded)
+}
+
+/// Makes a single path segment safe to use as a file or folder name on all platforms.
+fn sanitize(segment: &str) -> String {
+    const RESERVED: [&str; 22] = [
+        "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+        "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+    ];
+
+    let mut sanitized: String = segment
+        .chars()
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '\\' | '|' | '?' | '*' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .collect();
+
+    // Windows doesn't allow names ending in a dot or space, and dots alone would leave the
+    // export directory.
+    while sanitized.ends_with('.') || sanitized.ends_with(' ') {
+        sanitized.pop();
+    }
+    if sanitized.is_empty() {
+        sanitized.push('_');
+    }
+
+    let stem = sanitized.split('.').next().unwrap_or_default();
+    if RESERVED.iter().any(|name| stem.eq_ignore_ascii_case(name)) {
+        sanitized.insert(0, '_');
+    }
+
+    sanitized
+}
+
+/// Today's date in UTC, as `YYYY-MM-DD`.
+fn date_today() -> String {
+    let days = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs() / 86_400)
+        .unwrap_or_default() as i64;
+
+    // Converts days since the epoch to a civil date, from Howard Hinnant's `civil_from_days`.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SetExportTemplate {
+    pub from: ExportTemplate,
+    pub to: ExportTemplate,
+}
+impl UndoCommand for SetExportTemplate {
+    fn forward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        world.insert_resource(self.to.clone());
+    }
+
+    fn backward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        world.insert_resource(self.from.clone());
+    }
+}
+
+pub(crate) struct ExportTemplatePlugin;
+
+impl Plugin for ExportTemplatePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ExportTemplate::default())
+            .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Update)
+                    .after(CustomStage::Setup)
+                    .with_system(
+                        edit_template_enter
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::ExportTemplate))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        edit_template_update
+                            .system()
+                            .with_run_criteria(State::on_update(ToolState::ExportTemplate))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        edit_template_exit
+                            .system()
+                            .with_run_criteria(State::on_exit(ToolState::ExportTemplate))
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            );
+    }
+}
+
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::ExportTemplate));
+}
+
+fn edit_template_enter(
+    mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
+    mut text_field: ResMut<TextField>,
+    export_template: Res<ExportTemplate>,
+) {
+    if let Ok(mut instructions) = q_instructions.get_single_mut() {
+        let input = TextInput::new(export_template.0.clone(), Validation::Text);
+
+        instructions.sections[0].value = "Tokens: {project} {output} {width} {height} {date}\n\
+            Use / for subfolders\n\n\
+            Export name template: "
+            .into();
+        instructions.sections[1].value = input.display();
+        **text_field = Some(input);
+    }
+}
+
+fn edit_template_update(
+    mut char_input_events: EventReader<ReceivedCharacter>,
+    mut scan_code_input: ResMut<ScanCodeInput>,
+    mut text_field: ResMut<TextField>,
+    mut tool_state: ResMut<State<ToolState>>,
+    mut q_instructions: Query<&mut Text, With<InstructionMarker>>,
+    export_template: Res<ExportTemplate>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+) {
+    if let (Ok(mut instructions), Some(input)) =
+        (q_instructions.get_single_mut(), text_field.0.as_mut())
+    {
+        match input.update(&mut char_input_events, &mut scan_code_input) {
+            Some(TextInputEvent::Submit(text)) => {
+                let to = ExportTemplate(text);
+
+                match to.path(UNTITLED_PROJECT, "output", CoreSize::new(1, 1)) {
+                    Ok(_) => {
+                        undo_command_manager.push(Box::new(SetExportTemplate {
+                            from: export_template.clone(),
+                            to,
+                        }));
+                        undo_command_manager.push(Box::new(Checkpoint));
+                        tool_state.overwrite_replace(ToolState::None).unwrap();
+                    }
+                    Err(e) => {
+                        input.set_error(format!("Invalid export name template: {}", e));
+                        instructions.sections[1].value = input.display();
+                    }
+                }
+            }
+            Some(TextInputEvent::Cancel) => tool_state.overwrite_replace(ToolState::None).unwrap(),
+            None => instructions.sections[1].value = input.display(),
+        }
+    }
+}
+
+fn edit_template_exit(mut text_field: ResMut<TextField>) {
+    **text_field = None;
+}
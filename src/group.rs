@@ -0,0 +1,624 @@
+/// Grouping nodes into `Graph` nodes, and entering groups to edit the nodes inside of them.
+///
+/// The nodes in a group are kept as a `Project` in `GroupContents`, and the `NodeGraph` of the
+/// `Graph` node is built from it. Entering a group swaps the workspace for the nodes in the group,
+/// and leaving it brings back the workspace it was entered from, with the `Graph` node rebuilt if
+/// anything in the group changed. Each level has its own undo history.
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::*;
+use vismut_core::{
+    edge::Edge,
+    live_graph::LiveGraph,
+    node::{node_type::NodeType, Node, Slot, SlotType},
+    node_graph::{NodeId, SlotId},
+};
+
+use crate::{
+    delete_tool::DeleteSelected,
+    embed::EmbeddedImages,
+    export_format::ExportFormats,
+    instruction::{InstructId, Instructions, ToolList},
+    keymap::{Action, Keymap},
+    mouse_interaction::{active::Active, select::ReplaceSelection},
+    normal_map::NormalMaps,
+    notification::Notifications,
+    project::{load_nodes, workspace_project, Project, ProjectNode},
+    shared::NodeIdComponent,
+    sync_graph::{Edge as GuiEdge, InputCounts, NODE_SIZE},
+    thumbnail::ThumbnailExposures,
+    undo::{
+        edge::AddEdge,
+        node::{AddNode, RemoveNode},
+        prelude::*,
+        undo_command_manager::BoxUndoCommand,
+    },
+    AmbiguitySet, CustomStage, Selected, ToolState,
+};
+
+/// The nodes inside of each `Graph` node that was made by grouping, with their positions.
+#[derive(Debug, Default)]
+pub(crate) struct GroupContents(pub HashMap<NodeId, Project>);
+
+impl std::ops::Deref for GroupContents {
+    type Target = HashMap<NodeId, Project>;
+    fn deref(&self) -> &HashMap<NodeId, Project> {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for GroupContents {
+    fn deref_mut(&mut self) -> &mut HashMap<NodeId, Project> {
+        &mut self.0
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SetGroupContents {
+    pub node_id: NodeId,
+    pub from: Option<Project>,
+    pub to: Option<Project>,
+}
+impl UndoCommand for SetGroupContents {
+    fn forward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        set_group_contents(world, self.node_id, self.to.clone());
+    }
+
+    fn backward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        set_group_contents(world, self.node_id, self.from.clone());
+    }
+}
+
+fn set_group_contents(world: &mut World, node_id: NodeId, project: Option<Project>) {
+    if let Some(mut group_contents) = world.get_resource_mut::<GroupContents>() {
+        match project {
+            Some(project) => group_contents.insert(node_id, project),
+            None => group_contents.remove(&node_id),
+        };
+    }
+}
+
+/// A workspace that a group was entered from.
+#[derive(Debug)]
+struct GroupLevel {
+    /// The `Graph` node that was entered.
+    node_id: NodeId,
+    parent: Project,
+    undo_stack: Vec<BoxUndoCommand>,
+    redo_stack: Vec<BoxUndoCommand>,
+}
+
+/// The groups that have been entered, outermost first. Empty when the root of the project is in
+/// the workspace.
+#[derive(Debug, Default)]
+pub(crate) struct GroupStack(Vec<GroupLevel>);
+
+impl GroupStack {
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// Puts the project of the entered group back into each level above it, and returns the
+    /// project of the root.
+    pub fn root_project(&self, project: Project) -> Project {
+        self.0.iter().rev().fold(project, |group, level| {
+            let mut parent = level.parent.clone();
+            if let Some(project_node) = parent
+                .nodes
+                .iter_mut()
+                .find(|project_node| project_node.node.node_id == level.node_id)
+            {
+                project_node.group = Some(Box::new(group));
+            }
+            parent
+        })
+    }
+
+    fn breadcrumbs(&self) -> String {
+        self.0
+            .iter()
+            .fold("Root".to_string(), |breadcrumbs, level| {
+                format!("{} > Group {}", breadcrumbs, level.node_id.0)
+            })
+    }
+}
+
+/// Replaces the workspace with the nodes in the group of a `Graph` node.
+#[derive(Debug)]
+pub(crate) struct EnterGroup(pub NodeId);
+impl UndoCommand for EnterGroup {
+    fn command_type(&self) -> UndoCommandType {
+        UndoCommandType::Custom
+    }
+
+    fn forward(&self, world: &mut World, undo_command_manager: &mut UndoCommandManager) {
+        let group = match world
+            .get_resource::<GroupContents>()
+            .and_then(|group_contents| group_contents.get(&self.0).cloned())
+        {
+            Some(group) => group,
+            None => return,
+        };
+        let parent = match workspace_project(world) {
+            Ok(parent) => parent,
+            Err(e) => {
+                error!("Unable to enter the group: {}", e);
+                return;
+            }
+        };
+
+        undo_command_manager.command_batch.clear();
+        let level = GroupLevel {
+            node_id: self.0,
+            parent,
+            undo_stack: std::mem::take(&mut undo_command_manager.undo_stack),
+            redo_stack: std::mem::take(&mut undo_command_manager.redo_stack),
+        };
+        world
+            .get_resource_mut::<GroupStack>()
+            .unwrap()
+            .0
+            .push(level);
+
+        load_nodes(world, &group, undo_command_manager);
+    }
+
+    fn backward(&self, _: &mut World, _: &mut UndoCommandManager) {
+        unreachable!("this command is never put on the undo stack");
+    }
+}
+
+/// Brings back the workspace the current group was entered from. If the group was changed, the
+/// `Graph` node is replaced with one built from the new contents, which can be undone.
+#[derive(Debug)]
+pub(crate) struct LeaveGroup;
+impl UndoCommand for LeaveGroup {
+    fn command_type(&self) -> UndoCommandType {
+        UndoCommandType::Custom
+    }
+
+    fn forward(&self, world: &mut World, undo_command_manager: &mut UndoCommandManager) {
+        let level = match world
+            .get_resource_mut::<GroupStack>()
+            .and_then(|mut group_stack| group_stack.0.pop())
+        {
+            Some(level) => level,
+            None => return,
+        };
+        let group = match workspace_project(world) {
+            Ok(group) => group,
+            Err(e) => {
+                error!("Unable to leave the group: {}", e);
+                world
+                    .get_resource_mut::<GroupStack>()
+                    .unwrap()
+                    .0
+                    .push(level);
+                return;
+            }
+        };
+
+        load_nodes(world, &level.parent, undo_command_manager);
+        undo_command_manager.command_batch.clear();
+        undo_command_manager.undo_stack = level.undo_stack;
+        undo_command_manager.redo_stack = level.redo_stack;
+
+        let project_node = match level
+            .parent
+            .nodes
+            .iter()
+            .find(|project_node| project_node.node.node_id == level.node_id)
+        {
+            Some(project_node) => project_node,
+            None => return,
+        };
+        let old_group = project_node.group.as_deref().cloned();
+        if old_group.as_ref().and_then(|old| old.to_json().ok()) == group.to_json().ok() {
+            return;
+        }
+
+        let live_graph = Arc::clone(world.get_resource::<Arc<RwLock<LiveGraph>>>().unwrap());
+        let mut live_graph = live_graph.write().unwrap();
+        let old_node = live_graph.node(level.node_id).unwrap();
+        let node_graph = match group.node_graph(&mut live_graph) {
+            Ok(node_graph) => node_graph,
+            Err(e) => {
+                error!("Unable to update the group: {}", e);
+                return;
+            }
+        };
+        let new_node = Node {
+            node_type: NodeType::Graph(node_graph),
+            ..old_node.clone()
+        };
+        drop(live_graph);
+
+        let translation = project_node.translation.into();
+        let mut commands: Vec<BoxUndoCommand> = vec![
+            Box::new(RemoveNode::new(old_node, translation)),
+            Box::new(SetGroupContents {
+                node_id: level.node_id,
+                from: old_group,
+                to: Some(group),
+            }),
+            Box::new(AddNode::new(new_node, translation)),
+        ];
+        // Slots that are gone from the new graph can't be connected, and are left out.
+        for edge in level
+            .parent
+            .edges
+            .iter()
+            .filter(|edge| edge.output_id == level.node_id || edge.input_id == level.node_id)
+        {
+            commands.push(Box::new(AddEdge(*edge)));
+        }
+        commands.push(Box::new(Checkpoint));
+        undo_command_manager.push_front_vec(commands);
+    }
+
+    fn backward(&self, _: &mut World, _: &mut UndoCommandManager) {
+        unreachable!("this command is never put on the undo stack");
+    }
+}
+
+pub(crate) struct GroupPlugin;
+
+impl Plugin for GroupPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GroupContents::default())
+            .insert_resource(GroupStack::default())
+            .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Update)
+                    .after(CustomStage::Setup)
+                    .with_system(
+                        group
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::Group))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        enter_group
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::EnterGroup))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        leave_group
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::LeaveGroup))
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            )
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Apply)
+                    .after(CustomStage::Update)
+                    .with_system(breadcrumbs.system().in_ambiguity_set(AmbiguitySet)),
+            );
+    }
+}
+
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Group));
+    tool_list.insert(keymap.help(Action::EnterGroup));
+    tool_list.insert(keymap.help(Action::LeaveGroup));
+}
+
+/// The commands that replace the selected nodes with a `Graph` node containing them.
+///
+/// Edges going into the selection get an input node in the group, and edges coming out of it get
+/// an output node. One is made for each output slot they come from, so an output connected to
+/// several selected nodes only needs one input. Each slot of the `Graph` node has the id of the
+/// input or output node it belongs to, which is how the edges outside of the group find them.
+#[allow(clippy::too_many_arguments)]
+fn group_commands(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    input_counts: &InputCounts,
+    export_formats: &ExportFormats,
+    embedded_images: &EmbeddedImages,
+    thumbnail_exposures: &ThumbnailExposures,
+    normal_maps: &NormalMaps,
+    group_contents: &GroupContents,
+    q_selected: &Query<(&NodeIdComponent, &Transform), With<Selected>>,
+    q_edge: &Query<&GuiEdge>,
+) -> Result<Vec<BoxUndoCommand>> {
+    let nodes = q_selected
+        .iter()
+        .map(|(node_id, transform)| (node_id.0, transform.translation.truncate()))
+        .collect::<Vec<(NodeId, Vec2)>>();
+    if nodes.is_empty() {
+        bail!("no nodes are selected");
+    }
+
+    let is_selected = |node_id: NodeId| nodes.iter().any(|(id, _)| *id == node_id);
+    let center = nodes
+        .iter()
+        .fold(Vec2::ZERO, |sum, (_, translation)| sum + *translation)
+        / nodes.len() as f32;
+    let (min_x, max_x) = nodes.iter().fold(
+        (f32::INFINITY, f32::NEG_INFINITY),
+        |(min_x, max_x), (_, translation)| (min_x.min(translation.x), max_x.max(translation.x)),
+    );
+
+    let edges = q_edge
+        .iter()
+        .map(|gui_edge| Edge::from(*gui_edge))
+        .collect::<Vec<Edge>>();
+    let inner_edges = edges
+        .iter()
+        .filter(|edge| is_selected(edge.output_id) && is_selected(edge.input_id))
+        .copied()
+        .collect();
+    let incoming = edges
+        .iter()
+        .filter(|edge| !is_selected(edge.output_id) && is_selected(edge.input_id))
+        .copied()
+        .collect::<Vec<Edge>>();
+    let outgoing = edges
+        .iter()
+        .filter(|edge| is_selected(edge.output_id) && !is_selected(edge.input_id))
+        .copied()
+        .collect::<Vec<Edge>>();
+
+    let mut live_graph = live_graph.write().map_err(|e| anyhow!("{}", e))?;
+    let mut project = Project::new(
+        &live_graph,
+        input_counts,
+        export_formats,
+        embedded_images,
+        thumbnail_exposures,
+        normal_maps,
+        group_contents,
+        nodes
+            .iter()
+            .map(|(node_id, translation)| (*node_id, *translation - center)),
+        inner_edges,
+    )?;
+
+    // The ids only have to be unique within the group.
+    let mut next_id = nodes
+        .iter()
+        .map(|(node_id, _)| node_id.0)
+        .max()
+        .unwrap_or_default()
+        + 1;
+    // The output slots outside and inside of the group, with the input or output node made for
+    // each of them.
+    let mut sources: Vec<((NodeId, SlotId), NodeId)> = Vec::new();
+    let mut targets: Vec<((NodeId, SlotId), NodeId)> = Vec::new();
+
+    for edge in &incoming {
+        if sources
+            .iter()
+            .any(|(source, _)| *source == (edge.output_id, edge.output_slot))
+        {
+            continue;
+        }
+
+        let input_id = NodeId(next_id);
+        next_id += 1;
+        sources.push(((edge.output_id, edge.output_slot), input_id));
+
+        let name = format!("in {}", sources.len());
+        let node_type = match output_slot_type(&live_graph, edge.output_id, edge.output_slot)? {
+            SlotType::Gray => NodeType::InputGray(name),
+            SlotType::Rgba | SlotType::GrayOrRgba => NodeType::InputRgba(name),
+        };
+
+        project.nodes.push(boundary_node(
+            input_id,
+            node_type,
+            Vec2::new(
+                min_x - center.x - NODE_SIZE * 2.0,
+                -NODE_SIZE * 1.5 * (sources.len() - 1) as f32,
+            ),
+        ));
+        for inner_edge in incoming.iter().filter(|inner_edge| {
+            (inner_edge.output_id, inner_edge.output_slot) == (edge.output_id, edge.output_slot)
+        }) {
+            project.edges.push(Edge {
+                output_id: input_id,
+                output_slot: SlotId(0),
+                ..*inner_edge
+            });
+        }
+    }
+
+    for edge in &outgoing {
+        if targets
+            .iter()
+            .any(|(target, _)| *target == (edge.output_id, edge.output_slot))
+        {
+            continue;
+        }
+
+        let output_id = NodeId(next_id);
+        next_id += 1;
+        targets.push(((edge.output_id, edge.output_slot), output_id));
+
+        let name = format!("out {}", targets.len());
+        let node_type = match output_slot_type(&live_graph, edge.output_id, edge.output_slot)? {
+            SlotType::Gray => NodeType::OutputGray(name),
+            SlotType::Rgba | SlotType::GrayOrRgba => NodeType::OutputRgba(name),
+        };
+
+        project.nodes.push(boundary_node(
+            output_id,
+            node_type,
+            Vec2::new(
+                max_x - center.x + NODE_SIZE * 2.0,
+                -NODE_SIZE * 1.5 * (targets.len() - 1) as f32,
+            ),
+        ));
+        project.edges.push(Edge {
+            input_id: output_id,
+            input_slot: SlotId(0),
+            ..*edge
+        });
+    }
+
+    let graph_id = live_graph.new_id();
+    let mut graph_node = Node::new(NodeType::Graph(project.node_graph(&mut live_graph)?));
+    graph_node.node_id = graph_id;
+    let input_slots = graph_node.input_slots();
+    let output_slots = graph_node.output_slots();
+
+    let mut commands: Vec<BoxUndoCommand> = vec![
+        Box::new(SetGroupContents {
+            node_id: graph_id,
+            from: None,
+            to: Some(project),
+        }),
+        Box::new(DeleteSelected),
+        Box::new(AddNode::new(graph_node, center)),
+    ];
+    for edge in incoming {
+        let slot = boundary_slot(&sources, &input_slots, edge)
+            .ok_or_else(|| anyhow!("the group is missing an input"))?;
+        commands.push(Box::new(AddEdge(Edge {
+            input_id: graph_id,
+            input_slot: slot.slot_id,
+            ..edge
+        })));
+    }
+    for edge in outgoing {
+        let slot = boundary_slot(&targets, &output_slots, edge)
+            .ok_or_else(|| anyhow!("the group is missing an output"))?;
+        commands.push(Box::new(AddEdge(Edge {
+            output_id: graph_id,
+            output_slot: slot.slot_id,
+            ..edge
+        })));
+    }
+    commands.push(Box::new(ReplaceSelection(vec![graph_id])));
+    commands.push(Box::new(Checkpoint));
+
+    Ok(commands)
+}
+
+/// The slot of the `Graph` node that belongs to the input or output node made for the output slot
+/// `edge` comes from.
+fn boundary_slot<'a>(
+    boundary_nodes: &[((NodeId, SlotId), NodeId)],
+    slots: &'a [Slot],
+    edge: Edge,
+) -> Option<&'a Slot> {
+    let (_, node_id) = boundary_nodes
+        .iter()
+        .find(|(source, _)| *source == (edge.output_id, edge.output_slot))?;
+    slots.iter().find(|slot| slot.slot_id == SlotId(node_id.0))
+}
+
+fn output_slot_type(live_graph: &LiveGraph, node_id: NodeId, slot_id: SlotId) -> Result<SlotType> {
+    live_graph
+        .node(node_id)?
+        .output_slots()
+        .into_iter()
+        .find(|slot| slot.slot_id == slot_id)
+        .map(|slot| slot.slot_type)
+        .ok_or_else(|| anyhow!("the slot {:?} doesn't exist", slot_id))
+}
+
+/// An input or output node for an edge that crosses the edge of a group.
+fn boundary_node(node_id: NodeId, node_type: NodeType, translation: Vec2) -> ProjectNode {
+    let mut node = Node::new(node_type);
+    node.node_id = node_id;
+
+    ProjectNode {
+        node,
+        translation: translation.into(),
+        input_count: None,
+        export_format: None,
+        embedded_image: None,
+        thumbnail_exposure: None,
+        normal_map: None,
+        group: None,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn group(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    mut notifications: ResMut<Notifications>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    input_counts: Res<InputCounts>,
+    export_formats: Res<ExportFormats>,
+    embedded_images: Res<EmbeddedImages>,
+    thumbnail_exposures: Res<ThumbnailExposures>,
+    normal_maps: Res<NormalMaps>,
+    group_contents: Res<GroupContents>,
+    q_selected: Query<(&NodeIdComponent, &Transform), With<Selected>>,
+    q_edge: Query<&GuiEdge>,
+) {
+    match group_commands(
+        &*live_graph,
+        &*input_counts,
+        &*export_formats,
+        &*embedded_images,
+        &*thumbnail_exposures,
+        &*normal_maps,
+        &*group_contents,
+        &q_selected,
+        &q_edge,
+    ) {
+        Ok(commands) => {
+            for command in commands {
+                undo_command_manager.push(command);
+            }
+        }
+        Err(e) => notifications.error(format!("Unable to group: {}", e)),
+    }
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+fn enter_group(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    mut notifications: ResMut<Notifications>,
+    group_contents: Res<GroupContents>,
+    q_active: Query<&NodeIdComponent, With<Active>>,
+) {
+    match q_active.get_single() {
+        Ok(node_id) if group_contents.contains_key(&node_id.0) => {
+            undo_command_manager.push(Box::new(EnterGroup(node_id.0)));
+        }
+        _ => notifications.error("The active node is not a group"),
+    }
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+fn leave_group(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    group_stack: Res<GroupStack>,
+) {
+    if !group_stack.is_empty() {
+        undo_command_manager.push(Box::new(LeaveGroup));
+    }
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+fn breadcrumbs(group_stack: Res<GroupStack>, mut instructions: ResMut<Instructions>) {
+    if group_stack.is_changed() {
+        if group_stack.is_empty() {
+            instructions.remove(&InstructId::Group);
+        } else {
+            instructions.insert(
+                InstructId::Group,
+                format!("\nGroup: {}", group_stack.breadcrumbs()),
+            );
+        }
+    }
+}
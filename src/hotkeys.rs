@@ -3,7 +3,9 @@ use bevy::{app::AppExit, prelude::*, window::WindowFocused};
 use crate::{
     camera::FirstPersonState,
     delete_tool::DeleteSelected,
+    keymap::{Action, Context, Keymap},
     scan_code_input::{ScanCode, ScanCodeInput},
+    text_input::TextField,
     undo::{
         prelude::{Checkpoint, UndoCommandManager},
         undo_command_manager::UndoCancel,
@@ -37,8 +39,15 @@ fn focus_change(
     }
 }
 
-fn quit_hotkey(input: Res<ScanCodeInput>, mut app_exit_events: EventWriter<AppExit>) {
-    if control_pressed(&input) && input.just_pressed(ScanCode::KeyQ) {
+fn quit_hotkey(
+    input: Res<ScanCodeInput>,
+    keymap: Res<Keymap>,
+    mut app_exit_events: EventWriter<AppExit>,
+) {
+    if input
+        .get_just_pressed()
+        .any(|scan_code| keymap.action(Context::Always, *scan_code, &input) == Some(Action::Quit))
+    {
         app_exit_events.send(AppExit);
     }
 }
@@ -47,10 +56,10 @@ pub(crate) fn control_pressed(scan_code_input: &ScanCodeInput) -> bool {
     scan_code_input.pressed(ScanCode::ControlLeft)
         || scan_code_input.pressed(ScanCode::ControlRight)
 }
-fn shift_pressed(scan_code_input: &ScanCodeInput) -> bool {
+pub(crate) fn shift_pressed(scan_code_input: &ScanCodeInput) -> bool {
     scan_code_input.pressed(ScanCode::ShiftLeft) || scan_code_input.pressed(ScanCode::ShiftRight)
 }
-fn alt_pressed(scan_code_input: &ScanCodeInput) -> bool {
+pub(crate) fn alt_pressed(scan_code_input: &ScanCodeInput) -> bool {
     scan_code_input.pressed(ScanCode::AltLeft) || scan_code_input.pressed(ScanCode::AltRight)
 }
 
@@ -75,8 +84,12 @@ fn hotkeys(
     i_mouse_button: Res<Input<MouseButton>>,
     mut sc_input: ResMut<ScanCodeInput>,
     mut undo_command_manager: ResMut<UndoCommandManager>,
+    keymap: Res<Keymap>,
+    text_field: Res<TextField>,
 ) {
-    if sc_input.just_pressed(ScanCode::Backquote) {
+    if sc_input.get_just_pressed().any(|scan_code| {
+        keymap.action(Context::Always, *scan_code, &sc_input) == Some(Action::FirstPerson)
+    }) {
         if *first_person_state.current() == FirstPersonState::Off {
             first_person_state.set(FirstPersonState::On).unwrap();
         } else {
@@ -89,56 +102,56 @@ fn hotkeys(
     let mut just_released_scan_code = Vec::new();
     if tool_current == ToolState::None {
         for scan_code in sc_input.get_just_pressed() {
-            let new_tool = match scan_code {
-                ScanCode::Delete | ScanCode::KeyX => {
+            let action = match keymap.action(Context::NoTool, *scan_code, &sc_input) {
+                Some(action) => action,
+                None => continue,
+            };
+
+            let new_tool = match action {
+                Action::Delete => {
                     undo_command_manager.push(Box::new(DeleteSelected));
                     undo_command_manager.push(Box::new(Checkpoint));
                     None
                 }
-                ScanCode::F12 => Some(tool_state.set(ToolState::Process)),
-                ScanCode::KeyA => {
-                    if shift_pressed(&sc_input) {
-                        Some(tool_state.set(ToolState::Add))
-                    } else {
-                        None
-                    }
-                }
-                ScanCode::KeyE => {
-                    if control_pressed(&sc_input) {
-                        if shift_pressed(&sc_input) {
-                            Some(tool_state.set(ToolState::ExportOutputs(true)))
-                        } else {
-                            Some(tool_state.set(ToolState::ExportOutputs(false)))
-                        }
-                    } else if alt_pressed(&sc_input) && shift_pressed(&sc_input) {
-                        Some(tool_state.set(ToolState::Export))
-                    } else {
-                        None
-                    }
-                }
-                ScanCode::KeyG => Some(tool_state.set(ToolState::Grab(GrabToolType::Node))),
-                ScanCode::Tab => Some(tool_state.set(ToolState::EditNode)),
-                ScanCode::KeyZ => {
-                    if control_pressed(&sc_input) {
-                        if shift_pressed(&sc_input) {
-                            Some(tool_state.set(ToolState::Redo))
-                        } else {
-                            Some(tool_state.set(ToolState::Undo))
-                        }
-                    } else {
-                        None
-                    }
-                }
+                Action::Add => Some(ToolState::Add),
+                Action::Copy => Some(ToolState::Copy),
+                Action::Cut => Some(ToolState::Cut),
+                Action::Duplicate => Some(ToolState::Duplicate),
+                Action::EditNode => Some(ToolState::EditNode),
+                Action::EnterGroup => Some(ToolState::EnterGroup),
+                Action::Export => Some(ToolState::Export),
+                Action::ExportOutputs => Some(ToolState::ExportOutputs(false)),
+                Action::ExportOutputsAs => Some(ToolState::ExportOutputs(true)),
+                Action::ExportTemplate => Some(ToolState::ExportTemplate),
+                Action::FrameAll => Some(ToolState::Frame(true)),
+                Action::FrameSelected => Some(ToolState::Frame(false)),
+                Action::Grab => Some(ToolState::Grab(GrabToolType::Node)),
+                Action::Group => Some(ToolState::Group),
+                Action::LeaveGroup => Some(ToolState::LeaveGroup),
+                Action::Open => Some(ToolState::Open),
+                Action::PackingPreset => Some(ToolState::PackingPreset),
+                Action::Paste => Some(ToolState::Paste),
+                Action::Process => Some(ToolState::Process),
+                Action::ProcessingMode => Some(ToolState::ProcessingMode),
+                Action::Redo => Some(ToolState::Redo),
+                Action::Relink => Some(ToolState::Relink),
+                Action::Save => Some(ToolState::Save(false)),
+                Action::SaveAs => Some(ToolState::Save(true)),
+                Action::Undo => Some(ToolState::Undo),
                 _ => None,
             };
 
             if let Some(new_tool) = new_tool {
                 just_released_scan_code.push(*scan_code);
-                new_tool.unwrap();
+                tool_state.set(new_tool).unwrap();
                 break;
             }
         }
-    } else if cancel_just_pressed(&sc_input, &i_mouse_button) && tool_current != ToolState::None {
+    } else if cancel_just_pressed(&sc_input, &i_mouse_button)
+        && tool_current != ToolState::None
+        // A text field uses escape to cancel just the typing.
+        && !(text_field.is_some() && sc_input.just_pressed(ScanCode::Escape))
+    {
         undo_command_manager.push(Box::new(UndoCancel));
         tool_state.overwrite_replace(ToolState::None).unwrap();
     }
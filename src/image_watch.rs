@@ -0,0 +1,120 @@
+/// Reloading `Image` nodes when their files change on disk.
+///
+/// Bevy's file watcher only covers the asset folder, so the files of `Image` nodes are polled
+/// instead, which is cheap since it only reads their modification times.
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+    time::SystemTime,
+};
+
+use anyhow::{anyhow, Result};
+use bevy::prelude::*;
+use vismut_core::{live_graph::LiveGraph, node::node_type::NodeType, node_graph::NodeId};
+
+use crate::{
+    core_translation::reprocess, node_error::NodeError, shared::NodeIdComponent, CustomStage,
+};
+
+/// How often the files are checked, in seconds.
+const POLL_INTERVAL: f32 = 0.5;
+const MISSING_FILE: &str = "Missing file";
+
+/// When each watched file was last modified, `None` if it's missing.
+#[derive(Debug, Default)]
+struct WatchedFiles(HashMap<NodeId, (PathBuf, Option<SystemTime>)>);
+
+struct PollTimer(Timer);
+
+pub(crate) struct ImageWatchPlugin;
+
+impl Plugin for ImageWatchPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(WatchedFiles::default())
+            .insert_resource(PollTimer(Timer::from_seconds(POLL_INTERVAL, true)))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .after(CustomStage::Apply)
+                    .with_system(watch_images.system()),
+            );
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+fn missing_file_error(path: &Path) -> String {
+    format!("{}: {:?}", MISSING_FILE, path)
+}
+
+/// Marks a node and everything depending on it as dirty, so it's processed again.
+fn mark_dirty(live_graph: &Arc<RwLock<LiveGraph>>, node_id: NodeId) -> Result<()> {
+    let mut live_graph = live_graph.write().map_err(|e| anyhow!("{}", e))?;
+    // Sets `NodeType::Image(path)` again the same way editing the path does. The core only sees
+    // that the node was changed, and the file is read again when the node is processed.
+    reprocess(&mut live_graph, node_id)
+}
+
+fn watch_images(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut poll_timer: ResMut<PollTimer>,
+    mut watched_files: ResMut<WatchedFiles>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    q_node: Query<(Entity, &NodeIdComponent, Option<&NodeError>)>,
+) {
+    if !poll_timer.0.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    let mut still_watched = Vec::new();
+
+    for (node_e, node_id, node_error) in q_node.iter() {
+        let path = match live_graph
+            .read()
+            .ok()
+            .and_then(|live_graph| live_graph.node(node_id.0).ok())
+            .map(|node| node.node_type)
+        {
+            Some(NodeType::Image(path)) => path,
+            _ => continue,
+        };
+        still_watched.push(node_id.0);
+
+        let modified = modified(&path);
+        let changed = match watched_files.0.get(&node_id.0) {
+            // The path of the node changed, so it has already been reprocessed.
+            Some((old_path, _)) if *old_path != path => false,
+            Some((_, old_modified)) => *old_modified != modified,
+            None => false,
+        };
+
+        if modified.is_none() {
+            let error = missing_file_error(&path);
+            if node_error.map_or(true, |node_error| node_error.0 != error) {
+                commands.entity(node_e).insert(NodeError(error));
+            }
+        } else if node_error.map_or(false, |node_error| node_error.0.starts_with(MISSING_FILE)) {
+            commands.entity(node_e).remove::<NodeError>();
+        }
+
+        if changed && modified.is_some() {
+            info!("Reloading {:?}", path);
+            if let Err(e) = mark_dirty(&live_graph, node_id.0) {
+                warn!("Unable to reload {:?}: {}", path, e);
+            }
+        }
+
+        watched_files.0.insert(node_id.0, (path, modified));
+    }
+
+    watched_files
+        .0
+        .retain(|node_id, _| still_watched.contains(node_id));
+}
@@ -0,0 +1,1151 @@
+/// A panel on the right side of the window that shows the parameters of the active node, and lets
+/// them be changed with the mouse. Lists open when clicked, and fields are typed in after clicking
+/// them, like any other `TextInput`. Numbers can also be dragged left and right, which changes the
+/// node as you drag, hold `Shift` for finer steps and `Ctrl` to snap. Right click or `Esc` cancels
+/// the drag. Dragging the left half of the size changes its width, and the right half its height.
+/// Every change goes through the undo system.
+use std::{
+    path::Path,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::*;
+use vismut_core::{
+    live_graph::LiveGraph,
+    node::{mix::MixType, node_type::NodeType, Node, ResizeFilter, ResizePolicy},
+    node_graph::{NodeId, SlotId},
+    slot_data::{ChannelPixel, Size as TPSize},
+};
+
+use crate::{
+    core_translation::Translator,
+    hotkeys::{control_pressed, shift_pressed},
+    listable::Listable,
+    mouse_interaction::active::Active,
+    node_registry::{self, Parameter},
+    normal_map::{GreenChannel, NormalMap, NormalMaps, SetNormalMap},
+    notification::Notifications,
+    project::ProjectPath,
+    scan_code_input::ScanCodeInput,
+    shared::NodeIdComponent,
+    text_input::{parse_size, TextField, TextInput, TextInputEvent, Validation},
+    undo::{gui::GuiUndoCommand, prelude::*},
+    workspace::Workspace,
+    AmbiguitySet, CustomStage, ToolState,
+};
+
+pub(crate) const PANEL_WIDTH: f32 = 260.0;
+const LABEL_WIDTH: f32 = 90.0;
+const MARGIN: f32 = 8.0;
+const FONT_SIZE: f32 = 18.0;
+
+/// How far the cursor has to move, in pixels, before a click on a number becomes a drag.
+const SCRUB_THRESHOLD: f32 = 3.0;
+/// How much a number changes per pixel the cursor is dragged.
+const SCRUB_SPEED: f32 = 0.005;
+const SCRUB_SPEED_SLOT: f32 = 0.05;
+const SCRUB_SPEED_SIZE: f32 = 1.0;
+/// How much slower numbers change when holding shift.
+const SCRUB_FINE: f32 = 0.1;
+/// The step values snap to when holding control, sizes snap to powers of two.
+const SCRUB_SNAP: f32 = 0.1;
+
+const PANEL_COLOR: Color = Color::rgba(0.15, 0.15, 0.15, 0.9);
+const FIELD_COLOR: Color = Color::rgb(0.25, 0.25, 0.25);
+const FIELD_FOCUSED_COLOR: Color = Color::rgb(0.2, 0.3, 0.45);
+const OPTION_COLOR: Color = Color::rgb(0.2, 0.2, 0.2);
+
+#[derive(Component)]
+struct InspectorPanel;
+
+/// The contents of the panel, which are rebuilt when the active node changes.
+#[derive(Component)]
+struct InspectorItem;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum Field {
+    ResizePolicy,
+    ResizeFilter,
+    MixType,
+    Size,
+    Slot,
+    Value,
+    Name,
+    Path,
+    Strength,
+    GreenChannel,
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Choice {
+    ResizePolicy(ResizePolicy),
+    ResizeFilter(ResizeFilter),
+    MixType(MixType),
+    GreenChannel(GreenChannel),
+}
+
+#[derive(Component, Clone, Copy, Debug)]
+enum InspectorButton {
+    /// Opens or closes the list of choices for a field.
+    Dropdown(Field),
+    Choose(Choice),
+    /// Starts typing in a field.
+    Type(Field),
+}
+
+/// What dragging a field changes.
+#[derive(Clone, Debug)]
+enum Scrubbed {
+    Value,
+    /// The index of the slot among the node's input slots.
+    Slot(Vec<SlotId>),
+    /// The width of the size, keeping its height.
+    Width(TPSize),
+    /// The height of the size, keeping its width.
+    Height(TPSize),
+    /// The strength of a normal map, keeping its other settings.
+    Strength(NormalMap),
+}
+
+impl Scrubbed {
+    fn speed(&self) -> f32 {
+        match self {
+            Self::Value | Self::Strength(_) => SCRUB_SPEED,
+            Self::Slot(_) => SCRUB_SPEED_SLOT,
+            Self::Width(_) | Self::Height(_) => SCRUB_SPEED_SIZE,
+        }
+    }
+
+    /// Turns the dragged number into one the field can have.
+    fn round(&self, number: f32, snap: bool) -> f32 {
+        match self {
+            Self::Value | Self::Strength(_) if snap => (number / SCRUB_SNAP).round() * SCRUB_SNAP,
+            Self::Value | Self::Strength(_) => number,
+            Self::Slot(slots) => number
+                .round()
+                .clamp(0.0, slots.len().saturating_sub(1) as f32),
+            Self::Width(_) | Self::Height(_) if snap => 2_f32.powf(number.max(1.0).log2().round()),
+            Self::Width(_) | Self::Height(_) => number.round().max(1.0),
+        }
+    }
+
+    /// The resize policy the number stands for, or `None` for a value or strength.
+    fn policy(&self, number: f32) -> Option<ResizePolicy> {
+        match self {
+            Self::Value | Self::Strength(_) => None,
+            Self::Slot(slots) => slots
+                .get(number as usize)
+                .map(|slot_id| ResizePolicy::SpecificSlot(*slot_id)),
+            Self::Width(size) => Some(ResizePolicy::SpecificSize(TPSize::new(
+                number as u32,
+                size.height,
+            ))),
+            Self::Height(size) => Some(ResizePolicy::SpecificSize(TPSize::new(
+                size.width,
+                number as u32,
+            ))),
+        }
+    }
+
+    fn set(
+        &self,
+        live_graph: &mut LiveGraph,
+        normal_maps: &mut NormalMaps,
+        node_id: NodeId,
+        number: f32,
+    ) -> Result<()> {
+        match (self, self.policy(number)) {
+            (Self::Strength(normal_map), _) => {
+                normal_maps.insert(
+                    node_id,
+                    NormalMap {
+                        strength: number,
+                        ..*normal_map
+                    },
+                );
+                Ok(())
+            }
+            (_, Some(policy)) => node_id.set(live_graph, policy),
+            (_, None) => node_id.set(live_graph, number),
+        }
+    }
+
+    fn command(&self, node_id: NodeId, from: f32, to: f32) -> BoxUndoCommand {
+        match (self, self.policy(from), self.policy(to)) {
+            (Self::Strength(normal_map), _, _) => Box::new(SetNormalMap {
+                node_id,
+                from: NormalMap {
+                    strength: from,
+                    ..*normal_map
+                },
+                to: NormalMap {
+                    strength: to,
+                    ..*normal_map
+                },
+            }),
+            (_, Some(from), Some(to)) => Box::new(GuiUndoCommand::new(node_id, from, to)),
+            _ => Box::new(GuiUndoCommand::new(node_id, from, to)),
+        }
+    }
+}
+
+/// A number being dragged.
+#[derive(Debug)]
+struct Scrub {
+    field: Field,
+    scrubbed: Scrubbed,
+    node_id: NodeId,
+    from: f32,
+    /// The number without rounding.
+    value: f32,
+    /// The number the node has now.
+    shown: f32,
+    cursor_x: f32,
+    moved: bool,
+}
+
+#[derive(Debug, Default)]
+struct InspectorState {
+    open_dropdown: Option<Field>,
+    /// The field being typed in, the text is in the `TextField`.
+    typing: Option<Field>,
+    scrub: Option<Scrub>,
+}
+
+pub(crate) struct InspectorPlugin;
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(InspectorState::default())
+            .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Input)
+                    .with_system(cursor_over_panel.system().in_ambiguity_set(AmbiguitySet)),
+            )
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Update)
+                    .after(CustomStage::Setup)
+                    .in_ambiguity_set(AmbiguitySet)
+                    .with_system(click.system())
+                    .with_system(
+                        type_text
+                            .system()
+                            .with_run_criteria(State::on_update(ToolState::Inspect)),
+                    )
+                    .with_system(
+                        type_exit
+                            .system()
+                            .with_run_criteria(State::on_exit(ToolState::Inspect)),
+                    )
+                    .with_system(
+                        scrub
+                            .system()
+                            .with_run_criteria(State::on_update(ToolState::Scrub)),
+                    )
+                    .with_system(
+                        scrub_exit
+                            .system()
+                            .with_run_criteria(State::on_exit(ToolState::Scrub)),
+                    ),
+            )
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Apply)
+                    .after(CustomStage::Update)
+                    .with_system(update_panel.system().in_ambiguity_set(AmbiguitySet)),
+            );
+    }
+}
+
+fn setup(mut commands: Commands) {
+    commands
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                display: Display::None,
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(0.0),
+                    right: Val::Px(0.0),
+                    ..Default::default()
+                },
+                size: Size::new(Val::Px(PANEL_WIDTH), Val::Percent(100.0)),
+                flex_direction: FlexDirection::ColumnReverse,
+                justify_content: JustifyContent::FlexStart,
+                padding: Rect::all(Val::Px(MARGIN)),
+                ..Default::default()
+            },
+            color: UiColor(PANEL_COLOR),
+            ..Default::default()
+        })
+        .insert(Interaction::default())
+        .insert(InspectorPanel);
+}
+
+/// Keeps clicks on the panel from reaching the nodes behind it.
+fn cursor_over_panel(
+    q_panel: Query<(&Interaction, &Style), With<InspectorPanel>>,
+    mut workspace: ResMut<Workspace>,
+) {
+    workspace.cursor_over_ui = q_panel.iter().any(|(interaction, style)| {
+        style.display != Display::None && *interaction != Interaction::None
+    });
+}
+
+fn active_node(
+    q_active: &Query<&NodeIdComponent, With<Active>>,
+    live_graph: &Arc<RwLock<LiveGraph>>,
+) -> Option<Node> {
+    let node_id = q_active.get_single().ok()?;
+    live_graph.read().ok()?.node(node_id.0).ok()
+}
+
+/// Rebuilds the contents of the panel when the active node or what's being edited changes.
+#[allow(clippy::too_many_arguments)]
+fn update_panel(
+    mut commands: Commands,
+    asset_server: Res<AssetServer>,
+    state: Res<InspectorState>,
+    text_field: Res<TextField>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    normal_maps: Res<NormalMaps>,
+    q_active: Query<&NodeIdComponent, With<Active>>,
+    mut q_panel: Query<(Entity, &mut Style), With<InspectorPanel>>,
+    q_items: Query<Entity, With<InspectorItem>>,
+    mut shown: Local<String>,
+) {
+    let node = active_node(&q_active, &live_graph);
+    let fingerprint = format!(
+        "{:?}{:?}",
+        node.as_ref().map(|node| (
+            node.node_id,
+            &node.node_type,
+            node.resize_policy,
+            node.resize_filter,
+            normal_maps.normal_map(node.node_id)
+        )),
+        (
+            state.open_dropdown,
+            state.typing,
+            state.scrub.as_ref().map(|scrub| scrub.field)
+        ),
+        *text_field
+    );
+    if fingerprint == *shown {
+        return;
+    }
+    *shown = fingerprint;
+
+    let (panel, mut style) = match q_panel.get_single_mut() {
+        Ok(panel) => panel,
+        Err(_) => return,
+    };
+    for entity in q_items.iter() {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let node = match node {
+        Some(node) => node,
+        None => {
+            style.display = Display::None;
+            return;
+        }
+    };
+    style.display = Display::Flex;
+
+    let font = asset_server.load("fonts/FiraSans-Regular.ttf");
+    let typing = state.typing.zip(text_field.0.as_ref());
+    let scrubbing = state.scrub.as_ref().map(|scrub| scrub.field);
+    let info = node_registry::info(&node.node_type);
+
+    commands.entity(panel).with_children(|parent| {
+        parent
+            .spawn_bundle(text(format!("# {}", info.name), &font))
+            .insert(InspectorItem);
+
+        spawn_dropdown(
+            parent,
+            &font,
+            &state,
+            Field::ResizePolicy,
+            node.resize_policy.to_string(),
+            choices(Choice::ResizePolicy),
+        );
+        match node.resize_policy {
+            ResizePolicy::SpecificSize(size) => spawn_field(
+                parent,
+                &font,
+                typing,
+                scrubbing,
+                Field::Size,
+                format!("{}x{}", size.width, size.height),
+            ),
+            ResizePolicy::SpecificSlot(slot_id) => {
+                let index = node
+                    .input_slots()
+                    .iter()
+                    .position(|slot| slot.slot_id == slot_id)
+                    .map_or_else(|| slot_id.to_string(), |index| index.to_string());
+                spawn_field(parent, &font, typing, scrubbing, Field::Slot, index);
+            }
+            _ => (),
+        }
+        spawn_dropdown(
+            parent,
+            &font,
+            &state,
+            Field::ResizeFilter,
+            node.resize_filter.to_string(),
+            choices(Choice::ResizeFilter),
+        );
+
+        match &node.node_type {
+            NodeType::Mix(mix_type) if info.has(Parameter::MixType) => spawn_dropdown(
+                parent,
+                &font,
+                &state,
+                Field::MixType,
+                mix_type.to_string(),
+                choices(Choice::MixType),
+            ),
+            NodeType::Value(value) if info.has(Parameter::Value) => spawn_field(
+                parent,
+                &font,
+                typing,
+                scrubbing,
+                Field::Value,
+                value.to_string(),
+            ),
+            NodeType::OutputRgba(name) | NodeType::OutputGray(name)
+                if info.has(Parameter::Name) =>
+            {
+                spawn_field(parent, &font, typing, scrubbing, Field::Name, name.clone())
+            }
+            NodeType::Image(path) if info.has(Parameter::Path) => spawn_field(
+                parent,
+                &font,
+                typing,
+                scrubbing,
+                Field::Path,
+                path.to_string_lossy().into_owned(),
+            ),
+            _ => (),
+        }
+
+        let normal_map = normal_maps.normal_map(node.node_id);
+        if info.has(Parameter::Strength) {
+            spawn_field(
+                parent,
+                &font,
+                typing,
+                scrubbing,
+                Field::Strength,
+                normal_map.strength.to_string(),
+            );
+        }
+        if info.has(Parameter::GreenChannel) {
+            spawn_dropdown(
+                parent,
+                &font,
+                &state,
+                Field::GreenChannel,
+                normal_map.green.to_string(),
+                choices(Choice::GreenChannel),
+            );
+        }
+    });
+}
+
+/// All choices of a listable type, labelled with how they are displayed.
+fn choices<T: Listable<T> + ToString>(choice: fn(T) -> Choice) -> Vec<(String, Choice)> {
+    (1..)
+        .map_while(T::choose)
+        .map(|value| (value.to_string(), choice(value)))
+        .collect()
+}
+
+fn label(field: Field) -> &'static str {
+    match field {
+        Field::ResizePolicy => "Resize policy",
+        Field::ResizeFilter => "Resize filter",
+        Field::MixType => "Mix type",
+        Field::Size => "Size",
+        Field::Slot => "Slot",
+        Field::Value => "Value",
+        Field::Name => "Name",
+        Field::Path => "Path",
+        Field::Strength => "Strength",
+        Field::GreenChannel => "Green",
+    }
+}
+
+fn text(value: impl Into<String>, font: &Handle<Font>) -> TextBundle {
+    TextBundle {
+        text: Text::with_section(
+            value,
+            TextStyle {
+                font: font.clone(),
+                font_size: FONT_SIZE,
+                color: Color::WHITE,
+            },
+            TextAlignment::default(),
+        ),
+        ..Default::default()
+    }
+}
+
+fn spawn_row(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    field: Field,
+    button: InspectorButton,
+    value: String,
+    color: Color,
+) {
+    parent
+        .spawn_bundle(NodeBundle {
+            style: Style {
+                margin: Rect {
+                    top: Val::Px(MARGIN),
+                    ..Default::default()
+                },
+                align_items: AlignItems::Center,
+                ..Default::default()
+            },
+            color: UiColor(Color::NONE),
+            ..Default::default()
+        })
+        .insert(InspectorItem)
+        .with_children(|row| {
+            row.spawn_bundle(NodeBundle {
+                style: Style {
+                    size: Size::new(Val::Px(LABEL_WIDTH), Val::Auto),
+                    ..Default::default()
+                },
+                color: UiColor(Color::NONE),
+                ..Default::default()
+            })
+            .with_children(|label_node| {
+                label_node.spawn_bundle(text(label(field), font));
+            });
+            row.spawn_bundle(ButtonBundle {
+                style: Style {
+                    flex_grow: 1.0,
+                    padding: Rect::all(Val::Px(MARGIN / 2.0)),
+                    ..Default::default()
+                },
+                color: UiColor(color),
+                ..Default::default()
+            })
+            .insert(button)
+            .with_children(|button| {
+                button.spawn_bundle(text(value, font));
+            });
+        });
+}
+
+fn spawn_dropdown(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    state: &InspectorState,
+    field: Field,
+    value: String,
+    choices: Vec<(String, Choice)>,
+) {
+    let open = state.open_dropdown == Some(field);
+    let color = if open {
+        FIELD_FOCUSED_COLOR
+    } else {
+        FIELD_COLOR
+    };
+    spawn_row(
+        parent,
+        font,
+        field,
+        InspectorButton::Dropdown(field),
+        format!("{} v", value),
+        color,
+    );
+
+    if open {
+        for (name, choice) in choices {
+            parent
+                .spawn_bundle(ButtonBundle {
+                    style: Style {
+                        margin: Rect {
+                            left: Val::Px(LABEL_WIDTH),
+                            ..Default::default()
+                        },
+                        padding: Rect::all(Val::Px(MARGIN / 2.0)),
+                        ..Default::default()
+                    },
+                    color: UiColor(OPTION_COLOR),
+                    ..Default::default()
+                })
+                .insert(InspectorButton::Choose(choice))
+                .insert(InspectorItem)
+                .with_children(|button| {
+                    button.spawn_bundle(text(name, font));
+                });
+        }
+    }
+}
+
+fn spawn_field(
+    parent: &mut ChildBuilder,
+    font: &Handle<Font>,
+    typing: Option<(Field, &TextInput)>,
+    scrubbing: Option<Field>,
+    field: Field,
+    value: String,
+) {
+    match typing {
+        Some((typing_field, input)) if typing_field == field => spawn_row(
+            parent,
+            font,
+            field,
+            InspectorButton::Type(field),
+            input.display(),
+            FIELD_FOCUSED_COLOR,
+        ),
+        _ if scrubbing == Some(field) => spawn_row(
+            parent,
+            font,
+            field,
+            InspectorButton::Type(field),
+            value,
+            FIELD_FOCUSED_COLOR,
+        ),
+        _ => spawn_row(
+            parent,
+            font,
+            field,
+            InspectorButton::Type(field),
+            value,
+            FIELD_COLOR,
+        ),
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn click(
+    q_buttons: Query<
+        (&Interaction, &InspectorButton, &GlobalTransform),
+        (Changed<Interaction>, With<Button>),
+    >,
+    mut state: ResMut<InspectorState>,
+    mut text_field: ResMut<TextField>,
+    mut tool_state: ResMut<State<ToolState>>,
+    workspace: Res<Workspace>,
+    q_active: Query<&NodeIdComponent, With<Active>>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    normal_maps: Res<NormalMaps>,
+    project_path: Res<ProjectPath>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let tool_current = tool_state.current().clone();
+    if tool_current != ToolState::None && tool_current != ToolState::Inspect {
+        return;
+    }
+
+    for (interaction, button, transform) in q_buttons.iter() {
+        if *interaction != Interaction::Clicked {
+            continue;
+        }
+        if let InspectorButton::Type(field) = button {
+            if state.typing == Some(*field) {
+                return;
+            }
+        }
+
+        let node_id = match q_active.get_single() {
+            Ok(node_id) => node_id.0,
+            Err(_) => return,
+        };
+
+        if let (Some(field), Some(input)) = (state.typing.take(), text_field.0.take()) {
+            apply_typed(
+                field,
+                input.text(),
+                node_id,
+                &live_graph,
+                &normal_maps,
+                &project_path,
+                &mut undo_command_manager,
+                &mut notifications,
+            );
+        }
+
+        match *button {
+            InspectorButton::Dropdown(field) => {
+                state.open_dropdown = if state.open_dropdown == Some(field) {
+                    None
+                } else {
+                    Some(field)
+                };
+            }
+            InspectorButton::Choose(choice) => {
+                state.open_dropdown = None;
+                if let Err(e) = apply_choice(
+                    choice,
+                    node_id,
+                    &live_graph,
+                    &normal_maps,
+                    &mut undo_command_manager,
+                ) {
+                    notifications.error(format!("Unable to set {}: {}", choice_name(choice), e));
+                }
+            }
+            InspectorButton::Type(field) => {
+                state.open_dropdown = None;
+                let left_half = workspace.cursor_screen.x < transform.translation.x;
+                if let Some(scrub) = start_scrub(
+                    field,
+                    node_id,
+                    &live_graph,
+                    &normal_maps,
+                    &workspace,
+                    left_half,
+                ) {
+                    state.scrub = Some(scrub);
+                    tool_state.overwrite_replace(ToolState::Scrub).unwrap();
+                    return;
+                }
+                let text = match live_graph.read() {
+                    Ok(live_graph) => current_text(field, &*live_graph, &normal_maps, node_id),
+                    Err(_) => String::new(),
+                };
+                state.typing = Some(field);
+                **text_field = Some(TextInput::new(text, validation(field)));
+            }
+        }
+
+        let tool_new = if state.typing.is_some() {
+            ToolState::Inspect
+        } else {
+            ToolState::None
+        };
+        if tool_new != tool_current {
+            tool_state.overwrite_replace(tool_new).unwrap();
+        }
+        return;
+    }
+}
+
+fn choice_name(choice: Choice) -> &'static str {
+    match choice {
+        Choice::ResizePolicy(_) => "resize policy",
+        Choice::ResizeFilter(_) => "resize filter",
+        Choice::MixType(_) => "mix type",
+        Choice::GreenChannel(_) => "green channel",
+    }
+}
+
+fn apply_choice(
+    choice: Choice,
+    node_id: NodeId,
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    normal_maps: &NormalMaps,
+    undo_command_manager: &mut UndoCommandManager,
+) -> Result<()> {
+    let live_graph = live_graph.read().map_err(|e| anyhow!("{}", e))?;
+
+    match choice {
+        Choice::ResizePolicy(to) => {
+            let node = live_graph.node(node_id)?;
+            let to = match (to, node.resize_policy) {
+                // Keep the size or slot if the policy already had one.
+                (ResizePolicy::SpecificSize(_), from @ ResizePolicy::SpecificSize(_))
+                | (ResizePolicy::SpecificSlot(_), from @ ResizePolicy::SpecificSlot(_)) => from,
+                (ResizePolicy::SpecificSlot(_), _) => match node.input_slots().first() {
+                    Some(slot) => ResizePolicy::SpecificSlot(slot.slot_id),
+                    None => bail!("the node doesn't have any input slots"),
+                },
+                _ => to,
+            };
+            undo_command_manager.push(Box::new(GuiUndoCommand::new(
+                node_id,
+                node.resize_policy,
+                to,
+            )));
+        }
+        Choice::ResizeFilter(to) => {
+            let from: ResizeFilter = node_id.get(&*live_graph)?;
+            undo_command_manager.push(Box::new(GuiUndoCommand::new(node_id, from, to)));
+        }
+        Choice::MixType(to) => {
+            let from: NodeType = node_id.get(&*live_graph)?;
+            undo_command_manager.push(Box::new(GuiUndoCommand::new(
+                node_id,
+                from,
+                NodeType::Mix(to),
+            )));
+        }
+        Choice::GreenChannel(green) => {
+            let from = normal_maps.normal_map(node_id);
+            undo_command_manager.push(Box::new(SetNormalMap {
+                node_id,
+                from,
+                to: NormalMap { green, ..from },
+            }));
+        }
+    }
+
+    undo_command_manager.push(Box::new(Checkpoint));
+    Ok(())
+}
+
+/// The text a field starts with when it's clicked.
+fn current_text(
+    field: Field,
+    live_graph: &LiveGraph,
+    normal_maps: &NormalMaps,
+    node_id: NodeId,
+) -> String {
+    let node = match live_graph.node(node_id) {
+        Ok(node) => node,
+        Err(_) => return String::new(),
+    };
+
+    match (field, &node.resize_policy, &node.node_type) {
+        (Field::Size, ResizePolicy::SpecificSize(size), _) => {
+            format!("{}x{}", size.width, size.height)
+        }
+        (Field::Slot, ResizePolicy::SpecificSlot(slot_id), _) => node
+            .input_slots()
+            .iter()
+            .position(|slot| slot.slot_id == *slot_id)
+            .map(|index| index.to_string())
+            .unwrap_or_default(),
+        (Field::Value, _, NodeType::Value(value)) => value.to_string(),
+        (Field::Name, _, NodeType::OutputRgba(name) | NodeType::OutputGray(name)) => name.clone(),
+        (Field::Path, _, NodeType::Image(path)) => path.to_string_lossy().into_owned(),
+        (Field::Strength, _, _) => normal_maps.normal_map(node_id).strength.to_string(),
+        _ => String::new(),
+    }
+}
+
+/// Starts dragging a number, if the field is one that can be dragged. The left half of the size
+/// field drags the width, and the right half the height.
+fn start_scrub(
+    field: Field,
+    node_id: NodeId,
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    normal_maps: &NormalMaps,
+    workspace: &Workspace,
+    left_half: bool,
+) -> Option<Scrub> {
+    let node = live_graph.read().ok()?.node(node_id).ok()?;
+    let (scrubbed, from) = match (field, &node.resize_policy, &node.node_type) {
+        (Field::Value, _, NodeType::Value(value)) => (Scrubbed::Value, *value),
+        (Field::Slot, ResizePolicy::SpecificSlot(slot_id), _) => {
+            let slots: Vec<SlotId> = node.input_slots().iter().map(|slot| slot.slot_id).collect();
+            let index = slots.iter().position(|slot| slot == slot_id)?;
+            (Scrubbed::Slot(slots), index as f32)
+        }
+        (Field::Size, ResizePolicy::SpecificSize(size), _) if left_half => {
+            (Scrubbed::Width(*size), size.width as f32)
+        }
+        (Field::Size, ResizePolicy::SpecificSize(size), _) => {
+            (Scrubbed::Height(*size), size.height as f32)
+        }
+        (Field::Strength, _, _) => {
+            let normal_map = normal_maps.normal_map(node_id);
+            (Scrubbed::Strength(normal_map), normal_map.strength)
+        }
+        _ => return None,
+    };
+
+    Some(Scrub {
+        field,
+        scrubbed,
+        node_id,
+        from,
+        value: from,
+        shown: from,
+        cursor_x: workspace.cursor_screen.x,
+        moved: false,
+    })
+}
+
+/// Changes the number while the mouse button is held, and makes it one undo step when it's
+/// released. If the cursor didn't move, it was a click, and the field is typed in instead.
+#[allow(clippy::too_many_arguments)]
+fn scrub(
+    i_mouse_button: Res<Input<MouseButton>>,
+    scan_code_input: Res<ScanCodeInput>,
+    workspace: Res<Workspace>,
+    mut state: ResMut<InspectorState>,
+    mut text_field: ResMut<TextField>,
+    mut tool_state: ResMut<State<ToolState>>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    mut normal_maps: ResMut<NormalMaps>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+) {
+    if !i_mouse_button.pressed(MouseButton::Left) {
+        if let Some(scrub) = state.scrub.take() {
+            if scrub.moved {
+                undo_command_manager.push(scrub.scrubbed.command(
+                    scrub.node_id,
+                    scrub.from,
+                    scrub.shown,
+                ));
+                undo_command_manager.push(Box::new(Checkpoint));
+            } else {
+                let text = match live_graph.read() {
+                    Ok(live_graph) => {
+                        current_text(scrub.field, &*live_graph, &normal_maps, scrub.node_id)
+                    }
+                    Err(_) => String::new(),
+                };
+                state.typing = Some(scrub.field);
+                **text_field = Some(TextInput::new(text, validation(scrub.field)));
+                tool_state.overwrite_replace(ToolState::Inspect).unwrap();
+                return;
+            }
+        }
+        tool_state.overwrite_replace(ToolState::None).unwrap();
+        return;
+    }
+
+    let scrub = match state.scrub.as_mut() {
+        Some(scrub) => scrub,
+        None => {
+            tool_state.overwrite_replace(ToolState::None).unwrap();
+            return;
+        }
+    };
+
+    let delta = workspace.cursor_screen.x - scrub.cursor_x;
+    if !scrub.moved && delta.abs() < SCRUB_THRESHOLD {
+        return;
+    }
+    scrub.moved = true;
+    scrub.cursor_x = workspace.cursor_screen.x;
+
+    let speed = if shift_pressed(&scan_code_input) {
+        scrub.scrubbed.speed() * SCRUB_FINE
+    } else {
+        scrub.scrubbed.speed()
+    };
+    scrub.value += delta * speed;
+    let value = scrub
+        .scrubbed
+        .round(scrub.value, control_pressed(&scan_code_input));
+
+    if value != scrub.shown {
+        scrub.shown = value;
+        if let Ok(mut live_graph) = live_graph.write() {
+            if let Err(e) =
+                scrub
+                    .scrubbed
+                    .set(&mut *live_graph, &mut normal_maps, scrub.node_id, value)
+            {
+                error!("unable to preview the value: {}", e);
+            }
+        }
+    }
+}
+
+/// Puts the number back if the drag was cancelled.
+fn scrub_exit(
+    mut state: ResMut<InspectorState>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    mut normal_maps: ResMut<NormalMaps>,
+) {
+    if let Some(scrub) = state.scrub.take() {
+        if scrub.moved {
+            if let Ok(mut live_graph) = live_graph.write() {
+                if let Err(e) = scrub.scrubbed.set(
+                    &mut *live_graph,
+                    &mut normal_maps,
+                    scrub.node_id,
+                    scrub.from,
+                ) {
+                    error!("unable to reset the value: {}", e);
+                }
+            }
+        }
+    }
+}
+
+fn validation(field: Field) -> Validation {
+    match field {
+        Field::Size => Validation::Size,
+        Field::Slot => Validation::Integer,
+        Field::Value | Field::Strength => Validation::Float,
+        _ => Validation::Text,
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn type_text(
+    mut char_input_events: EventReader<ReceivedCharacter>,
+    mut scan_code_input: ResMut<ScanCodeInput>,
+    i_mouse_button: Res<Input<MouseButton>>,
+    workspace: Res<Workspace>,
+    state: Res<InspectorState>,
+    mut text_field: ResMut<TextField>,
+    mut tool_state: ResMut<State<ToolState>>,
+    q_active: Query<&NodeIdComponent, With<Active>>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    normal_maps: Res<NormalMaps>,
+    project_path: Res<ProjectPath>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    mut notifications: ResMut<Notifications>,
+) {
+    let (node_id, field, input) = match (q_active.get_single(), state.typing, text_field.0.as_mut())
+    {
+        (Ok(node_id), Some(field), Some(input)) => (node_id.0, field, input),
+        _ => {
+            tool_state.overwrite_replace(ToolState::None).unwrap();
+            return;
+        }
+    };
+
+    // Clicking outside of the panel applies what has been typed, like pressing enter.
+    if i_mouse_button.just_pressed(MouseButton::Left) && !workspace.cursor_over_ui {
+        apply_typed(
+            field,
+            input.text(),
+            node_id,
+            &live_graph,
+            &normal_maps,
+            &project_path,
+            &mut undo_command_manager,
+            &mut notifications,
+        );
+        tool_state.overwrite_replace(ToolState::None).unwrap();
+        return;
+    }
+
+    match input.update(&mut char_input_events, &mut scan_code_input) {
+        Some(TextInputEvent::Submit(text)) => {
+            match try_apply_typed(
+                field,
+                text,
+                node_id,
+                &live_graph,
+                &normal_maps,
+                &project_path,
+                &mut undo_command_manager,
+            ) {
+                Ok(()) => tool_state.overwrite_replace(ToolState::None).unwrap(),
+                Err(e) => input.set_error(e.to_string()),
+            }
+        }
+        Some(TextInputEvent::Cancel) => tool_state.overwrite_replace(ToolState::None).unwrap(),
+        None => (),
+    }
+}
+
+fn type_exit(mut state: ResMut<InspectorState>, mut text_field: ResMut<TextField>) {
+    state.typing = None;
+    **text_field = None;
+}
+
+#[allow(clippy::too_many_arguments)]
+fn apply_typed(
+    field: Field,
+    text: String,
+    node_id: NodeId,
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    normal_maps: &NormalMaps,
+    project_path: &ProjectPath,
+    undo_command_manager: &mut UndoCommandManager,
+    notifications: &mut Notifications,
+) {
+    if let Err(e) = try_apply_typed(
+        field,
+        text,
+        node_id,
+        live_graph,
+        normal_maps,
+        project_path,
+        undo_command_manager,
+    ) {
+        notifications.error(format!(
+            "Unable to set {}: {}",
+            label(field).to_lowercase(),
+            e
+        ));
+    }
+}
+
+fn try_apply_typed(
+    field: Field,
+    text: String,
+    node_id: NodeId,
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    normal_maps: &NormalMaps,
+    project_path: &ProjectPath,
+    undo_command_manager: &mut UndoCommandManager,
+) -> Result<()> {
+    let live_graph = live_graph.read().map_err(|e| anyhow!("{}", e))?;
+
+    match field {
+        Field::Size => {
+            let size = parse_size(&text).ok_or_else(|| anyhow!("should be like 256x256"))?;
+            let from: ResizePolicy = node_id.get(&*live_graph)?;
+            undo_command_manager.push(Box::new(GuiUndoCommand::new(
+                node_id,
+                from,
+                ResizePolicy::SpecificSize(size),
+            )));
+        }
+        Field::Slot => {
+            let index: usize = text.parse()?;
+            let node = live_graph.node(node_id)?;
+            let slot = node
+                .input_slots()
+                .get(index)
+                .map(|slot| slot.slot_id)
+                .ok_or_else(|| anyhow!("there is no slot {}", index))?;
+            undo_command_manager.push(Box::new(GuiUndoCommand::new(
+                node_id,
+                node.resize_policy,
+                ResizePolicy::SpecificSlot(slot),
+            )));
+        }
+        Field::Value => {
+            let value: ChannelPixel = text.parse()?;
+            let from: ChannelPixel = node_id.get(&*live_graph)?;
+            undo_command_manager.push(Box::new(GuiUndoCommand::new(node_id, from, value)));
+        }
+        Field::Name => {
+            let from: String = node_id.get(&*live_graph)?;
+            undo_command_manager.push(Box::new(GuiUndoCommand::new(node_id, from, text)));
+        }
+        Field::Path => {
+            let from: NodeType = node_id.get(&*live_graph)?;
+            undo_command_manager.push(Box::new(GuiUndoCommand::new(
+                node_id,
+                from,
+                NodeType::Image(project_path.resolve(Path::new(&text))?),
+            )));
+        }
+        Field::Strength => {
+            let from = normal_maps.normal_map(node_id);
+            undo_command_manager.push(Box::new(SetNormalMap {
+                node_id,
+                from,
+                to: NormalMap {
+                    strength: text.parse()?,
+                    ..from
+                },
+            }));
+        }
+        Field::ResizePolicy | Field::ResizeFilter | Field::MixType | Field::GreenChannel => {
+            bail!("it's chosen from a list")
+        }
+    }
+
+    undo_command_manager.push(Box::new(Checkpoint));
+    Ok(())
+}
@@ -2,12 +2,18 @@ use std::collections::BTreeSet;
 
 use bevy::{prelude::*, utils::HashMap};
 
-use crate::{AmbiguitySet, CustomStage, ToolState};
+use crate::{
+    keymap::{Action, Keymap},
+    AmbiguitySet, CustomStage, ToolState,
+};
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub(crate) enum InstructId {
     FirstPerson,
     Tool,
+    Processing,
+    /// Which group is entered, if any.
+    Group,
 }
 
 #[derive(Debug, Default)]
@@ -61,10 +67,10 @@ impl Plugin for InstructionPlugin {
     }
 }
 
-fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>, keymap: Res<Keymap>) {
     let mut text = Text::default();
     text.sections.resize(
-        2,
+        4,
         TextSection {
             style: TextStyle {
                 font: asset_server.load("fonts/FiraSans-Regular.ttf"),
@@ -75,7 +81,7 @@ fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
         },
     );
     text.sections[InstructId::Tool as usize].value =
-        "Welcome to Vismut!\n\nShift A: Add node\n".to_string();
+        format!("Welcome to Vismut!\n\n{}\n", keymap.help(Action::Add));
 
     commands.spawn_bundle(UiCameraBundle::default());
     commands
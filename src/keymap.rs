@@ -0,0 +1,499 @@
+/// Hotkeys that can be rebound in a config file.
+///
+/// The default bindings can be overridden in `keymap.ron` in the user's config directory, for
+/// instance `~/.config/vismut/keymap.ron` on Linux. Only the actions that are in the file are
+/// overridden, so changes to the defaults in newer versions still apply to the rest. If the file
+/// doesn't exist, it's created without any bindings, with the defaults listed in comments.
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    hotkeys::{alt_pressed, control_pressed, shift_pressed},
+    scan_code_input::{ScanCode, ScanCodeInput},
+};
+
+const KEYMAP_FILE: &str = "keymap.ron";
+
+/// Where an action can be used.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Context {
+    /// Regardless of which tool is active.
+    Always,
+    /// When no tool is active.
+    NoTool,
+    /// In the edit tool, when nothing is being edited.
+    Edit,
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, Ord, PartialEq, PartialOrd, Serialize)]
+pub(crate) enum Action {
+    // Always
+    FirstPerson,
+    Quit,
+
+    // No tool
+    Add,
+    Copy,
+    Cut,
+    Delete,
+    Duplicate,
+    EditNode,
+    EnterGroup,
+    Export,
+    ExportOutputs,
+    ExportOutputsAs,
+    ExportTemplate,
+    FrameAll,
+    FrameSelected,
+    Grab,
+    Group,
+    LeaveGroup,
+    Open,
+    PackingPreset,
+    Paste,
+    Process,
+    ProcessingMode,
+    Redo,
+    Relink,
+    Save,
+    SaveAs,
+    Undo,
+
+    // Edit tool
+    EditAddInput,
+    EditEmbed,
+    EditExit,
+    EditExportFormat,
+    EditExposure,
+    EditExtract,
+    EditGreenChannel,
+    EditMixType,
+    EditName,
+    EditRemoveInput,
+    EditResizeFilter,
+    EditResizePolicy,
+    EditStrength,
+    EditValue,
+}
+
+impl Action {
+    pub fn context(&self) -> Context {
+        match self {
+            Self::FirstPerson | Self::Quit => Context::Always,
+            Self::Add
+            | Self::Copy
+            | Self::Cut
+            | Self::Delete
+            | Self::Duplicate
+            | Self::EditNode
+            | Self::EnterGroup
+            | Self::Export
+            | Self::ExportOutputs
+            | Self::ExportOutputsAs
+            | Self::ExportTemplate
+            | Self::FrameAll
+            | Self::FrameSelected
+            | Self::Grab
+            | Self::Group
+            | Self::LeaveGroup
+            | Self::Open
+            | Self::PackingPreset
+            | Self::Paste
+            | Self::Process
+            | Self::ProcessingMode
+            | Self::Redo
+            | Self::Relink
+            | Self::Save
+            | Self::SaveAs
+            | Self::Undo => Context::NoTool,
+            Self::EditAddInput
+            | Self::EditEmbed
+            | Self::EditExit
+            | Self::EditExportFormat
+            | Self::EditExposure
+            | Self::EditExtract
+            | Self::EditGreenChannel
+            | Self::EditMixType
+            | Self::EditName
+            | Self::EditRemoveInput
+            | Self::EditResizeFilter
+            | Self::EditResizePolicy
+            | Self::EditStrength
+            | Self::EditValue => Context::Edit,
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Self::FirstPerson => "First person",
+            Self::Quit => "Quit",
+            Self::Add => "Add node",
+            Self::Copy => "Copy selected",
+            Self::Cut => "Cut selected",
+            Self::Delete => "Delete selected",
+            Self::Duplicate => "Duplicate selected",
+            Self::EditNode => "Edit active",
+            Self::EnterGroup => "Enter group",
+            Self::Export => "Export active",
+            Self::ExportOutputs => "Export outputs",
+            Self::ExportOutputsAs => "Export outputs as",
+            Self::ExportTemplate => "Export name template",
+            Self::FrameAll => "Frame all",
+            Self::FrameSelected => "Frame selected",
+            Self::Grab => "Move selected",
+            Self::Group => "Group selected",
+            Self::LeaveGroup => "Leave group",
+            Self::Open => "Open project",
+            Self::PackingPreset => "Channel packing preset",
+            Self::Paste => "Paste",
+            Self::Process => "Process",
+            Self::ProcessingMode => "Toggle manual processing",
+            Self::Redo => "Redo",
+            Self::Relink => "Relink missing files",
+            Self::Save => "Save project",
+            Self::SaveAs => "Save project as",
+            Self::Undo => "Undo",
+            Self::EditAddInput => "Add input",
+            Self::EditEmbed => "Embed in project",
+            Self::EditExit => "Exit",
+            Self::EditExportFormat => "Export format",
+            Self::EditExposure => "Thumbnail exposure",
+            Self::EditExtract => "Extract to file",
+            Self::EditGreenChannel => "Green channel",
+            Self::EditMixType => "Type",
+            Self::EditName => "Name",
+            Self::EditRemoveInput => "Remove input",
+            Self::EditResizeFilter => "Resize filter",
+            Self::EditResizePolicy => "Resize policy",
+            Self::EditStrength => "Strength",
+            Self::EditValue => "Value",
+        }
+    }
+}
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// A key, and the modifiers that need to be held for it.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub(crate) struct Binding {
+    pub key: ScanCode,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub ctrl: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub shift: bool,
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub alt: bool,
+}
+
+impl Binding {
+    fn key(key: ScanCode) -> Self {
+        Self {
+            key,
+            ctrl: false,
+            shift: false,
+            alt: false,
+        }
+    }
+
+    fn ctrl(mut self) -> Self {
+        self.ctrl = true;
+        self
+    }
+
+    fn shift(mut self) -> Self {
+        self.shift = true;
+        self
+    }
+
+    fn alt(mut self) -> Self {
+        self.alt = true;
+        self
+    }
+
+    /// A binding matches if its key was just pressed and all of its modifiers are held.
+    fn matches(&self, scan_code: ScanCode, input: &ScanCodeInput) -> bool {
+        self.key == scan_code
+            && (!self.ctrl || control_pressed(input))
+            && (!self.shift || shift_pressed(input))
+            && (!self.alt || alt_pressed(input))
+    }
+
+    fn modifier_count(&self) -> usize {
+        [self.ctrl, self.shift, self.alt]
+            .iter()
+            .filter(|modifier| **modifier)
+            .count()
+    }
+}
+
+impl std::fmt::Display for Binding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl ")?;
+        }
+        if self.shift {
+            write!(f, "Shift ")?;
+        }
+        if self.alt {
+            write!(f, "Alt ")?;
+        }
+
+        let key = format!("{:?}", self.key);
+        let key = match self.key {
+            ScanCode::Backquote => "`",
+            ScanCode::Equal => "+",
+            ScanCode::Minus => "-",
+            ScanCode::NumpadAdd => "Numpad +",
+            ScanCode::NumpadSubtract => "Numpad -",
+            _ => key
+                .strip_prefix("Key")
+                .or_else(|| key.strip_prefix("Digit"))
+                .unwrap_or(&key),
+        };
+
+        write!(f, "{}", key)
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Keymap(BTreeMap<Action, Vec<Binding>>);
+
+impl Default for Keymap {
+    fn default() -> Self {
+        use ScanCode::*;
+
+        let bindings = [
+            (Action::FirstPerson, vec![Binding::key(Backquote)]),
+            (Action::Quit, vec![Binding::key(KeyQ).ctrl()]),
+            (Action::Add, vec![Binding::key(KeyA).shift()]),
+            (Action::Copy, vec![Binding::key(KeyC).ctrl()]),
+            (Action::Cut, vec![Binding::key(KeyX).ctrl()]),
+            (
+                Action::Delete,
+                vec![Binding::key(Delete), Binding::key(KeyX)],
+            ),
+            (Action::Duplicate, vec![Binding::key(KeyD).shift()]),
+            (Action::EditNode, vec![Binding::key(Tab)]),
+            (Action::EnterGroup, vec![Binding::key(Tab).ctrl()]),
+            (Action::Export, vec![Binding::key(KeyE).shift().alt()]),
+            (Action::ExportOutputs, vec![Binding::key(KeyE).ctrl()]),
+            (
+                Action::ExportOutputsAs,
+                vec![Binding::key(KeyE).ctrl().shift()],
+            ),
+            (
+                Action::ExportTemplate,
+                vec![Binding::key(KeyE).ctrl().alt()],
+            ),
+            (Action::FrameAll, vec![Binding::key(Home)]),
+            (Action::FrameSelected, vec![Binding::key(KeyF)]),
+            (Action::Grab, vec![Binding::key(KeyG)]),
+            (Action::Group, vec![Binding::key(KeyG).ctrl()]),
+            (Action::LeaveGroup, vec![Binding::key(Tab).ctrl().shift()]),
+            (Action::Open, vec![Binding::key(KeyO).ctrl()]),
+            (Action::PackingPreset, vec![Binding::key(KeyP).ctrl().alt()]),
+            (Action::Paste, vec![Binding::key(KeyV).ctrl()]),
+            (Action::Process, vec![Binding::key(F12)]),
+            (Action::ProcessingMode, vec![Binding::key(F12).shift()]),
+            (Action::Redo, vec![Binding::key(KeyZ).ctrl().shift()]),
+            (Action::Relink, vec![Binding::key(KeyR).ctrl().alt()]),
+            (Action::Save, vec![Binding::key(KeyS).ctrl()]),
+            (Action::SaveAs, vec![Binding::key(KeyS).ctrl().shift()]),
+            (Action::Undo, vec![Binding::key(KeyZ).ctrl()]),
+            (
+                Action::EditAddInput,
+                vec![Binding::key(Equal), Binding::key(NumpadAdd)],
+            ),
+            (Action::EditEmbed, vec![Binding::key(KeyM)]),
+            (Action::EditExit, vec![Binding::key(Tab)]),
+            (Action::EditExportFormat, vec![Binding::key(KeyE)]),
+            (Action::EditExposure, vec![Binding::key(KeyO)]),
+            (Action::EditExtract, vec![Binding::key(KeyX)]),
+            (Action::EditGreenChannel, vec![Binding::key(KeyG)]),
+            (Action::EditMixType, vec![Binding::key(KeyT)]),
+            (Action::EditName, vec![Binding::key(KeyN)]),
+            (
+                Action::EditRemoveInput,
+                vec![Binding::key(Minus), Binding::key(NumpadSubtract)],
+            ),
+            (Action::EditResizeFilter, vec![Binding::key(KeyF)]),
+            (Action::EditResizePolicy, vec![Binding::key(KeyR)]),
+            (Action::EditStrength, vec![Binding::key(KeyS)]),
+            (Action::EditValue, vec![Binding::key(KeyV)]),
+        ];
+
+        Self(bindings.into_iter().collect())
+    }
+}
+
+impl Keymap {
+    /// Returns the action in the given context that the just pressed `scan_code` triggers. If
+    /// several bindings match, the one with the most modifiers wins, so for instance `Ctrl X`
+    /// takes priority over `X`.
+    pub fn action(
+        &self,
+        context: Context,
+        scan_code: ScanCode,
+        input: &ScanCodeInput,
+    ) -> Option<Action> {
+        self.0
+            .iter()
+            .filter(|(action, _)| action.context() == context)
+            .flat_map(|(action, bindings)| bindings.iter().map(move |binding| (action, binding)))
+            .filter(|(_, binding)| binding.matches(scan_code, input))
+            .max_by_key(|(_, binding)| binding.modifier_count())
+            .map(|(action, _)| *action)
+    }
+
+    /// All actions in the given context.
+    pub fn actions(&self, context: Context) -> impl Iterator<Item = Action> + '_ {
+        self.0
+            .keys()
+            .copied()
+            .filter(move |action| action.context() == context)
+    }
+
+    /// The keys bound to an action, for showing to the user.
+    pub fn keys(&self, action: Action) -> String {
+        match self.0.get(&action) {
+            Some(bindings) if !bindings.is_empty() => bindings
+                .iter()
+                .map(|binding| binding.to_string())
+                .collect::<Vec<String>>()
+                .join("/"),
+            _ => "Unbound".to_string(),
+        }
+    }
+
+    /// A line of help text for an action, like "Ctrl S: Save project".
+    pub fn help(&self, action: Action) -> String {
+        format!("{}: {}", self.keys(action), action.description())
+    }
+
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("vismut").join(KEYMAP_FILE))
+    }
+
+    /// Loads the keymap from the user's config directory, or writes an empty keymap file there if
+    /// there isn't one.
+    fn load() -> Self {
+        let mut keymap = Self::default();
+
+        let path = match Self::path() {
+            Some(path) => path,
+            None => {
+                warn!("Unable to find the config directory, using the default keymap");
+                return keymap;
+            }
+        };
+
+        if path.exists() {
+            match read_overrides(&path) {
+                Ok(overrides) => {
+                    info!("Loaded keymap from {:?}", path);
+                    keymap.0.extend(overrides);
+                }
+                Err(e) => error!(
+                    "Unable to read the keymap {:?}, using the default: {}",
+                    path, e
+                ),
+            }
+        } else if let Err(e) = keymap.write_template(&path) {
+            warn!("Unable to write an empty keymap to {:?}: {}", path, e);
+        }
+
+        keymap
+    }
+
+    /// Writes a keymap file that doesn't override anything, but lists the bindings in comments so
+    /// they are easy to copy and change.
+    fn write_template(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+
+        let mut text = String::new();
+        text += "// Bindings added here replace the default ones, and actions that aren't listed\n";
+        text += "// keep their defaults. The defaults are:\n";
+        for (action, bindings) in &self.0 {
+            text += &format!("//     {:?}: {},\n", action, ron::ser::to_string(bindings)?);
+        }
+        text += "{\n}\n";
+        fs::write(path, text)?;
+
+        Ok(())
+    }
+}
+
+/// The name of an action in the keymap file. It's kept as text, since the file can name actions
+/// that don't exist any more. The names are bare identifiers like `Grab`, which RON only reads as
+/// text when asked for an identifier rather than a string.
+#[derive(Debug, Eq, Ord, PartialEq, PartialOrd)]
+struct ActionName(String);
+
+impl<'de> Deserialize<'de> for ActionName {
+    fn deserialize<D>(deserializer: D) -> std::result::Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        struct NameVisitor;
+
+        impl<'de> serde::de::Visitor<'de> for NameVisitor {
+            type Value = ActionName;
+
+            fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                write!(f, "the name of an action")
+            }
+
+            fn visit_str<E: serde::de::Error>(
+                self,
+                name: &str,
+            ) -> std::result::Result<ActionName, E> {
+                Ok(ActionName(name.to_string()))
+            }
+        }
+
+        deserializer.deserialize_identifier(NameVisitor)
+    }
+}
+
+/// Reads the bindings in the keymap file. Actions that don't exist, for instance ones that were
+/// removed in a newer version, are skipped with a warning so the rest of the file still applies.
+fn read_overrides(path: &Path) -> Result<BTreeMap<Action, Vec<Binding>>> {
+    let ron = fs::read_to_string(path)?;
+    let overrides: BTreeMap<ActionName, Vec<Binding>> =
+        ron::from_str(&ron).map_err(|e| anyhow!("{}", e))?;
+
+    Ok(overrides
+        .into_iter()
+        .filter_map(
+            |(ActionName(name), bindings)| match ron::from_str::<Action>(&name) {
+                Ok(action) => Some((action, bindings)),
+                Err(_) => {
+                    warn!(
+                        "Skipping unknown action {:?} in the keymap {:?}",
+                        name, path
+                    );
+                    None
+                }
+            },
+        )
+        .collect())
+}
+
+pub(crate) struct KeymapPlugin;
+
+impl Plugin for KeymapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Keymap::load());
+    }
+}
@@ -2,26 +2,45 @@
 pub mod add_tool;
 pub mod box_select;
 pub mod camera;
+pub mod clipboard;
 pub mod core_translation;
 pub mod delete_tool;
 pub mod deselect_tool;
 pub mod drag_drop;
-// This will be re-enabled when drag and drop is supported in Bevy on Windows.
-// pub mod drag_drop_import;
+// Drag and drop conflicts with the file dialogs on Windows.
+#[cfg(not(target_os = "windows"))]
+pub mod drag_drop_import;
 pub mod edit_node;
+pub mod embed;
 pub mod export;
+pub mod export_format;
 pub mod export_outputs;
+pub mod export_template;
+pub mod group;
 pub mod hotkeys;
 pub mod hoverable;
+pub mod image_watch;
+pub mod inspector;
 pub mod instruction;
+pub mod keymap;
 pub mod listable;
 pub mod material;
 pub mod mouse_interaction;
+pub mod node_error;
+pub mod node_registry;
 pub mod node_state;
 pub mod none_tool;
+pub mod normal_map;
+pub mod notification;
+pub mod packing;
+pub mod process_tool;
+pub mod project;
+pub mod relink;
+pub mod render;
 pub mod scan_code_input;
 pub mod shared;
 pub mod sync_graph;
+pub mod text_input;
 pub mod thumbnail;
 pub mod thumbnail_state;
 pub mod undo;
@@ -62,14 +81,37 @@ pub(crate) enum GrabToolType {
 pub(crate) enum ToolState {
     Add,
     BoxSelect,
+    Copy,
+    Cut,
+    Duplicate,
     EditNode,
+    /// Enters the group of the active `Graph` node.
+    EnterGroup,
     Export,
     /// If `true`, do "export as".
     ExportOutputs(bool),
+    ExportTemplate,
+    /// If `true`, frame all nodes, otherwise frame the selected ones.
+    Frame(bool),
     Grab(GrabToolType),
+    /// Replaces the selected nodes with a `Graph` node containing them.
+    Group,
+    /// Typing in a field of the inspector panel.
+    Inspect,
+    LeaveGroup,
     None,
+    Open,
+    PackingPreset,
+    Paste,
     Process,
+    /// Toggles between automatic and manual processing.
+    ProcessingMode,
     Redo,
+    Relink,
+    /// If `true`, do "save as".
+    Save(bool),
+    /// Dragging a number in the inspector panel.
+    Scrub,
     Undo,
 }
 
@@ -80,6 +122,11 @@ impl Default for ToolState {
 }
 
 fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(String::as_str) == Some(render::SUBCOMMAND) {
+        std::process::exit(render::run(&args[2..]));
+    }
+
     App::new()
         .insert_resource(WindowDescriptor {
             title: "Vismut".to_string(),
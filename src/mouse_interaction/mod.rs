@@ -46,6 +46,10 @@ fn mouse_interaction(
     q_dropped: Query<&Dropped>,
     workspace: Res<Workspace>,
 ) {
+    if workspace.cursor_over_ui {
+        return;
+    }
+
     let some_dropped = q_dropped.iter().count() > 0;
     let single_click = i_mouse_button.just_released(MouseButton::Left)
         && workspace.drag != Drag::Dropping
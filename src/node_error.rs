@@ -0,0 +1,182 @@
+/// Showing errors on the nodes they belong to.
+use bevy::prelude::*;
+use vismut_core::node_graph::NodeId;
+
+use crate::{
+    hoverable::{Hoverable, Hovered},
+    shared::NodeIdComponent,
+    sync_graph::SLOT_SIZE,
+    thumbnail::THUMBNAIL_SIZE,
+    CustomStage,
+};
+
+const BADGE_COLOR: Color = Color::rgb(0.9, 0.15, 0.1);
+
+/// The most recent error of a GUI node. It's removed when the node has been successfully
+/// processed again.
+#[derive(Component, Clone, Debug)]
+pub(crate) struct NodeError(pub String);
+
+/// Marks a GUI node that the graph was unable to process. Unlike `NodeError` it's removed as soon
+/// as the node is processed again, so it can tell whether processing can still make progress.
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub(crate) struct ProcessingFailed;
+
+#[derive(Component)]
+struct ErrorBadge;
+
+#[derive(Component)]
+struct ErrorTooltip;
+
+pub(crate) struct NodeErrorPlugin;
+
+impl Plugin for NodeErrorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set_to_stage(
+            CoreStage::Update,
+            SystemSet::new()
+                .after(CustomStage::Apply)
+                .with_system(add_error_badge.system())
+                .with_system(error_tooltip_text.system())
+                .with_system(remove_error_badge.system())
+                .with_system(error_tooltip_visibility.system()),
+        );
+    }
+}
+
+/// Sets or clears the error of the GUI node with the given `NodeId`.
+pub(crate) fn set_node_error(world: &mut World, node_id: NodeId, error: Option<String>) {
+    let node_e = world
+        .query::<(Entity, &NodeIdComponent)>()
+        .iter(world)
+        .find(|(_, node_id_component)| node_id_component.0 == node_id)
+        .map(|(entity, _)| entity);
+
+    if let Some(node_e) = node_e {
+        match error {
+            Some(error) => {
+                world.entity_mut(node_e).insert(NodeError(error));
+            }
+            None => {
+                world.entity_mut(node_e).remove::<NodeError>();
+            }
+        }
+    }
+}
+
+fn add_error_badge(
+    mut commands: Commands,
+    q_node: Query<(Entity, &NodeError), Added<NodeError>>,
+    asset_server: Res<AssetServer>,
+) {
+    for (node_e, node_error) in q_node.iter() {
+        let text_style = TextStyle {
+            font: asset_server.load("fonts/FiraSans-Regular.ttf"),
+            font_size: SLOT_SIZE,
+            color: Color::WHITE,
+        };
+        let text_alignment = TextAlignment {
+            horizontal: HorizontalAlign::Center,
+            vertical: VerticalAlign::Center,
+        };
+
+        commands.entity(node_e).with_children(|parent| {
+            parent
+                .spawn_bundle(SpriteBundle {
+                    transform: Transform::from_translation(Vec3::new(
+                        THUMBNAIL_SIZE / 2. - SLOT_SIZE * 1.5,
+                        -THUMBNAIL_SIZE / 2. - SLOT_SIZE / 2.,
+                        0.1,
+                    )),
+                    sprite: Sprite {
+                        color: BADGE_COLOR,
+                        custom_size: Some(Vec2::new(SLOT_SIZE, SLOT_SIZE)),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .insert(Hoverable)
+                .insert(ErrorBadge)
+                .with_children(|parent| {
+                    parent.spawn_bundle(Text2dBundle {
+                        text: Text::with_section("!", text_style.clone(), text_alignment),
+                        transform: Transform::from_translation(Vec3::new(0.0, 0.0, 0.0001)),
+                        ..Default::default()
+                    });
+
+                    parent
+                        .spawn_bundle(Text2dBundle {
+                            text: Text::with_section(
+                                node_error.0.clone(),
+                                TextStyle {
+                                    font_size: SLOT_SIZE * 0.75,
+                                    ..text_style
+                                },
+                                TextAlignment {
+                                    horizontal: HorizontalAlign::Left,
+                                    vertical: VerticalAlign::Top,
+                                },
+                            ),
+                            transform: Transform::from_translation(Vec3::new(
+                                0.0, -SLOT_SIZE, // Draw on top of nearby nodes.
+                                10.0,
+                            )),
+                            visibility: Visibility { is_visible: false },
+                            ..Default::default()
+                        })
+                        .insert(ErrorTooltip);
+                });
+        });
+    }
+}
+
+/// Keeps the tooltips up to date when the error of a node that already has a badge changes.
+fn error_tooltip_text(
+    q_node: Query<(&NodeError, &Children), Changed<NodeError>>,
+    q_badge: Query<&Children, With<ErrorBadge>>,
+    mut q_tooltip: Query<&mut Text, With<ErrorTooltip>>,
+) {
+    for (node_error, node_children) in q_node.iter() {
+        for badge_children in node_children
+            .iter()
+            .filter_map(|child| q_badge.get(*child).ok())
+        {
+            for child in badge_children.iter() {
+                if let Ok(mut text) = q_tooltip.get_mut(*child) {
+                    text.sections[0].value = node_error.0.clone();
+                }
+            }
+        }
+    }
+}
+
+fn remove_error_badge(
+    mut commands: Commands,
+    removed: RemovedComponents<NodeError>,
+    q_node: Query<&Children, Without<NodeError>>,
+    q_badge: Query<Entity, With<ErrorBadge>>,
+) {
+    for node_e in removed.iter() {
+        if let Ok(children) = q_node.get(node_e) {
+            for badge_e in children.iter().filter_map(|child| q_badge.get(*child).ok()) {
+                commands.entity(badge_e).despawn_recursive();
+            }
+        }
+    }
+}
+
+/// Shows the error message while the badge is hovered.
+fn error_tooltip_visibility(
+    q_badge: Query<(&Children, Option<&Hovered>), With<ErrorBadge>>,
+    mut q_tooltip: Query<&mut Visibility, With<ErrorTooltip>>,
+) {
+    for (children, hovered) in q_badge.iter() {
+        for child in children.iter() {
+            if let Ok(mut visibility) = q_tooltip.get_mut(*child) {
+                if visibility.is_visible != hovered.is_some() {
+                    visibility.is_visible = hovered.is_some();
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,246 @@
+/// What the GUI knows about each node type: its names, category, default construction, editable
+/// parameters and help text. The add tool, the edit tool and the nodes in the workspace all read
+/// from here, so a new node type only needs an entry in this file.
+use std::{fmt, path::PathBuf};
+
+use vismut_core::node::{mix::MixType, node_type::NodeType};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Category {
+    Input,
+    Output,
+    Channel,
+    Filter,
+    Graph,
+}
+
+impl fmt::Display for Category {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::Input => "Input",
+            Self::Output => "Output",
+            Self::Channel => "Channel",
+            Self::Filter => "Filter",
+            Self::Graph => "Graph",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Something about a node that is shown, and usually edited, in the edit tool.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Parameter {
+    /// The name of an output.
+    Name,
+    ExportFormat,
+    MixType,
+    /// Adding and removing inputs.
+    InputCount,
+    Value,
+    /// The file of an image. It's only shown, not edited.
+    Path,
+    /// The exposure of the thumbnail.
+    Exposure,
+    /// Turning an image node into an embedded one.
+    Embed,
+    /// Turning an embedded image node into an image node.
+    Extract,
+    /// How strong the slopes of a normal map are.
+    Strength,
+    /// Which way the green channel of a normal map points.
+    GreenChannel,
+}
+
+pub(crate) struct NodeTypeInfo {
+    /// Shown in the add tool and the edit tool.
+    pub name: &'static str,
+    /// Shown on the node, short enough to fit.
+    pub title: &'static str,
+    pub category: Category,
+    /// Other words the node type can be found with in the add tool, in lowercase.
+    pub search_terms: &'static str,
+    /// A sentence describing what the node does.
+    pub help: &'static str,
+    pub parameters: &'static [Parameter],
+    /// The node type as it's created by the add tool, `None` if it can't be added.
+    pub default: Option<fn() -> NodeType>,
+}
+
+impl NodeTypeInfo {
+    pub fn has(&self, parameter: Parameter) -> bool {
+        self.parameters.contains(&parameter)
+    }
+}
+
+static COMBINE_RGBA: NodeTypeInfo = NodeTypeInfo {
+    name: "Combine RGBA",
+    title: "Combine",
+    category: Category::Channel,
+    search_terms: "rgba channels merge",
+    help: "Puts gray images into the channels of an RGBA image.",
+    parameters: &[],
+    default: Some(|| NodeType::CombineRgba),
+};
+
+static EMBED: NodeTypeInfo = NodeTypeInfo {
+    name: "Embedded Image",
+    title: "Image",
+    category: Category::Input,
+    search_terms: "file texture",
+    help: "An image that is stored in the project file.",
+    parameters: &[Parameter::Exposure, Parameter::Extract],
+    default: None,
+};
+
+static GRAPH: NodeTypeInfo = NodeTypeInfo {
+    name: "Graph",
+    title: "Graph",
+    category: Category::Graph,
+    search_terms: "nested subgraph",
+    help: "A graph inside of this graph.",
+    parameters: &[],
+    default: None,
+};
+
+static HEIGHT_TO_NORMAL: NodeTypeInfo = NodeTypeInfo {
+    name: "Height to Normal",
+    title: "Normal",
+    category: Category::Filter,
+    search_terms: "normal map bump",
+    help: "Turns a height map into a normal map.",
+    parameters: &[Parameter::Strength, Parameter::GreenChannel],
+    default: Some(|| NodeType::HeightToNormal),
+};
+
+static IMAGE: NodeTypeInfo = NodeTypeInfo {
+    name: "Image",
+    title: "Image",
+    category: Category::Input,
+    search_terms: "file texture import",
+    help: "An image file, which is reloaded when it changes.",
+    parameters: &[Parameter::Path, Parameter::Exposure, Parameter::Embed],
+    default: Some(|| NodeType::Image(PathBuf::new())),
+};
+
+static INPUT_GRAY: NodeTypeInfo = NodeTypeInfo {
+    name: "Gray Input",
+    title: "Input",
+    category: Category::Input,
+    search_terms: "grayscale graph",
+    help: "A grayscale input of the graph.",
+    parameters: &[],
+    default: None,
+};
+
+static INPUT_RGBA: NodeTypeInfo = NodeTypeInfo {
+    name: "Input",
+    title: "Input",
+    category: Category::Input,
+    search_terms: "rgba graph",
+    help: "An RGBA input of the graph.",
+    parameters: &[],
+    default: None,
+};
+
+static MIX: NodeTypeInfo = NodeTypeInfo {
+    name: "Mix",
+    title: "Mix",
+    category: Category::Filter,
+    search_terms: "blend add subtract multiply divide",
+    help: "Blends its inputs together.",
+    parameters: &[Parameter::MixType, Parameter::InputCount],
+    default: Some(|| NodeType::Mix(MixType::Add)),
+};
+
+static OUTPUT_GRAY: NodeTypeInfo = NodeTypeInfo {
+    name: "Gray Output",
+    title: "Gray Output",
+    category: Category::Output,
+    search_terms: "grayscale single channel export",
+    help: "A grayscale image that is exported with a single channel.",
+    parameters: &[Parameter::Name, Parameter::ExportFormat],
+    default: Some(|| NodeType::OutputGray("untitled".into())),
+};
+
+static OUTPUT_RGBA: NodeTypeInfo = NodeTypeInfo {
+    name: "Output",
+    title: "Output",
+    category: Category::Output,
+    search_terms: "rgba export",
+    help: "An RGBA image that is exported.",
+    parameters: &[Parameter::Name, Parameter::ExportFormat],
+    default: Some(|| NodeType::OutputRgba("untitled".into())),
+};
+
+static SEPARATE_RGBA: NodeTypeInfo = NodeTypeInfo {
+    name: "Separate RGBA",
+    title: "Separate",
+    category: Category::Channel,
+    search_terms: "rgba channels split",
+    help: "Splits an RGBA image into one gray image per channel.",
+    parameters: &[],
+    default: Some(|| NodeType::SeparateRgba),
+};
+
+static VALUE: NodeTypeInfo = NodeTypeInfo {
+    name: "Value",
+    title: "Value",
+    category: Category::Input,
+    search_terms: "number constant color",
+    help: "A single gray value.",
+    parameters: &[Parameter::Value],
+    default: Some(|| NodeType::Value(1.0)),
+};
+
+static WRITE: NodeTypeInfo = NodeTypeInfo {
+    name: "Write",
+    title: "Write",
+    category: Category::Output,
+    search_terms: "file save",
+    help: "Writes its input to a file.",
+    parameters: &[],
+    default: None,
+};
+
+/// Every node type, in the order they are listed in the add tool.
+static NODE_TYPES: [&NodeTypeInfo; 13] = [
+    &IMAGE,
+    &EMBED,
+    &VALUE,
+    &INPUT_RGBA,
+    &INPUT_GRAY,
+    &MIX,
+    &HEIGHT_TO_NORMAL,
+    &SEPARATE_RGBA,
+    &COMBINE_RGBA,
+    &OUTPUT_RGBA,
+    &OUTPUT_GRAY,
+    &WRITE,
+    &GRAPH,
+];
+
+pub(crate) fn info(node_type: &NodeType) -> &'static NodeTypeInfo {
+    match node_type {
+        NodeType::CombineRgba => &COMBINE_RGBA,
+        NodeType::Embed(_) => &EMBED,
+        NodeType::Graph(_) => &GRAPH,
+        NodeType::HeightToNormal => &HEIGHT_TO_NORMAL,
+        NodeType::Image(_) => &IMAGE,
+        NodeType::InputGray(_) => &INPUT_GRAY,
+        NodeType::InputRgba(_) => &INPUT_RGBA,
+        NodeType::Mix(_) => &MIX,
+        NodeType::OutputGray(_) => &OUTPUT_GRAY,
+        NodeType::OutputRgba(_) => &OUTPUT_RGBA,
+        NodeType::SeparateRgba => &SEPARATE_RGBA,
+        NodeType::Value(_) => &VALUE,
+        NodeType::Write(_) => &WRITE,
+    }
+}
+
+/// The node types that can be added with the add tool.
+pub(crate) fn addable() -> impl Iterator<Item = &'static NodeTypeInfo> {
+    NODE_TYPES
+        .iter()
+        .copied()
+        .filter(|info| info.default.is_some())
+}
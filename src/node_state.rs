@@ -2,9 +2,13 @@ use bevy::prelude::*;
 use vismut_core::live_graph::NodeState;
 
 use crate::{
-    shared::NodeStateComponent, sync_graph::SLOT_SIZE, thumbnail::THUMBNAIL_SIZE, CustomStage,
+    process_tool::ProcessingMode, shared::NodeStateComponent, sync_graph::SLOT_SIZE,
+    thumbnail::THUMBNAIL_SIZE, CustomStage,
 };
 
+/// Tint of the state image of nodes that are waiting for a manual processing pass.
+const STALE_COLOR: Color = Color::rgb(1.0, 0.4, 0.2);
+
 struct StateImages {
     clean: Handle<Image>,
     dirty: Handle<Image>,
@@ -40,7 +44,8 @@ impl Plugin for NodeStatePlugin {
                 CoreStage::Update,
                 SystemSet::new()
                     .after(CustomStage::Apply)
-                    .with_system(add_state_image.system().chain(state_materials.system())),
+                    .with_system(add_state_image.system().chain(state_materials.system()))
+                    .with_system(stale_tint.system()),
             );
     }
 }
@@ -96,3 +101,26 @@ fn state_materials(
         }
     }
 }
+
+/// Tints the state image of dirty nodes when in manual processing mode, since they won't be
+/// processed until the user asks for it.
+fn stale_tint(
+    processing_mode: Res<ProcessingMode>,
+    q_node: Query<&NodeStateComponent>,
+    mut q_state_image: Query<(&Parent, &mut Sprite), With<StateImage>>,
+) {
+    for (parent, mut sprite) in q_state_image.iter_mut() {
+        if let Ok(node_state) = q_node.get(parent.0) {
+            let color =
+                if *processing_mode == ProcessingMode::Manual && node_state.0 == NodeState::Dirty {
+                    STALE_COLOR
+                } else {
+                    Color::WHITE
+                };
+
+            if sprite.color != color {
+                sprite.color = color;
+            }
+        }
+    }
+}
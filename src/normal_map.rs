@@ -0,0 +1,192 @@
+/// Settings for the normal maps made by Height to Normal nodes. The node in the core doesn't have
+/// any parameters, so the settings are applied to its image where it leaves the graph: in its
+/// thumbnail, and in the thumbnails and exports of output nodes connected straight to it.
+use std::{collections::HashMap, fmt};
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use vismut_core::{
+    edge::Edge, live_graph::LiveGraph, node::node_type::NodeType, node_graph::NodeId,
+};
+
+use crate::{
+    export_format::Pixels, listable::Listable, shared::NodeIdComponent,
+    sync_graph::Edge as GuiEdge, thumbnail::ThumbnailState, undo::prelude::*,
+};
+
+/// Which way the green channel of a normal map points. OpenGL and Blender use Y+, DirectX and
+/// Unreal use Y-.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub(crate) enum GreenChannel {
+    OpenGl,
+    DirectX,
+}
+
+impl Default for GreenChannel {
+    fn default() -> Self {
+        Self::OpenGl
+    }
+}
+
+impl fmt::Display for GreenChannel {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Self::OpenGl => "OpenGL (Y+)",
+            Self::DirectX => "DirectX (Y-)",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+impl Listable<Self> for GreenChannel {
+    fn list() -> String {
+        let mut output = "## Green channel\n".to_string();
+        let entries = vec![Self::OpenGl.to_string(), Self::DirectX.to_string()];
+        for (i, entry) in entries.iter().enumerate() {
+            output = format!("{}{}: {}\n", output, i + 1, entry);
+        }
+        output
+    }
+
+    fn choose(i: usize) -> Option<Self> {
+        const MAX_CHOICE: usize = 2;
+
+        if i <= MAX_CHOICE {
+            Some(match i {
+                1 => Self::OpenGl,
+                _ => Self::DirectX,
+            })
+        } else {
+            None
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, PartialEq, Serialize)]
+pub(crate) struct NormalMap {
+    /// How much the slopes of the normals are scaled, 1 leaves them as the core makes them.
+    pub strength: f32,
+    pub green: GreenChannel,
+}
+
+impl Default for NormalMap {
+    fn default() -> Self {
+        Self {
+            strength: 1.0,
+            green: GreenChannel::default(),
+        }
+    }
+}
+
+impl NormalMap {
+    /// Scales the slopes of the normals in RGB or RGBA pixels, and flips green for DirectX. The
+    /// normals are normalized again afterwards. Alpha and grayscale pixels are left alone.
+    pub fn apply(&self, pixels: &mut Pixels) {
+        if *self == Self::default() {
+            return;
+        }
+
+        let (pixels, channel_count) = match pixels {
+            Pixels::Rgb(pixels) => (pixels, 3),
+            Pixels::Rgba(pixels) => (pixels, 4),
+            Pixels::Gray(_) => return,
+        };
+        let green = match self.green {
+            GreenChannel::OpenGl => 1.0,
+            GreenChannel::DirectX => -1.0,
+        };
+
+        for pixel in pixels.chunks_exact_mut(channel_count) {
+            let x = (pixel[0] * 2.0 - 1.0) * self.strength;
+            let y = (pixel[1] * 2.0 - 1.0) * self.strength * green;
+            let z = pixel[2] * 2.0 - 1.0;
+            let length = (x * x + y * y + z * z).sqrt();
+            if length > 0.0 {
+                pixel[0] = x / length * 0.5 + 0.5;
+                pixel[1] = y / length * 0.5 + 0.5;
+                pixel[2] = z / length * 0.5 + 0.5;
+            }
+        }
+    }
+}
+
+/// The normal map settings of Height to Normal nodes. Nodes that are not in here use
+/// `NormalMap::default()`.
+#[derive(Debug, Default)]
+pub(crate) struct NormalMaps(pub HashMap<NodeId, NormalMap>);
+
+impl std::ops::Deref for NormalMaps {
+    type Target = HashMap<NodeId, NormalMap>;
+    fn deref(&self) -> &HashMap<NodeId, NormalMap> {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for NormalMaps {
+    fn deref_mut(&mut self) -> &mut HashMap<NodeId, NormalMap> {
+        &mut self.0
+    }
+}
+
+impl NormalMaps {
+    pub fn normal_map(&self, node_id: NodeId) -> NormalMap {
+        self.get(&node_id).copied().unwrap_or_default()
+    }
+
+    /// The settings the image of a node is shown and exported with. Output nodes use the ones of
+    /// the Height to Normal node connected to them, so the exported file looks like the
+    /// thumbnail of the node it comes from.
+    pub fn applied(&self, live_graph: &LiveGraph, node_id: NodeId, edges: &[Edge]) -> NormalMap {
+        match live_graph.node(node_id).map(|node| node.node_type) {
+            Ok(NodeType::HeightToNormal) => self.normal_map(node_id),
+            Ok(NodeType::OutputRgba(_)) => edges
+                .iter()
+                .find(|edge| edge.input_id == node_id)
+                .map(|edge| self.normal_map(edge.output_id))
+                .unwrap_or_default(),
+            _ => NormalMap::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SetNormalMap {
+    pub node_id: NodeId,
+    pub from: NormalMap,
+    pub to: NormalMap,
+}
+impl UndoCommand for SetNormalMap {
+    fn forward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        set_normal_map(world, self.node_id, self.to);
+    }
+
+    fn backward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        set_normal_map(world, self.node_id, self.from);
+    }
+}
+
+/// Sets the normal map settings, and remakes the thumbnails of the node and the nodes connected
+/// to it.
+fn set_normal_map(world: &mut World, node_id: NodeId, normal_map: NormalMap) {
+    if let Some(mut normal_maps) = world.get_resource_mut::<NormalMaps>() {
+        normal_maps.insert(node_id, normal_map);
+    }
+
+    let mut affected = world
+        .query::<&GuiEdge>()
+        .iter(world)
+        .filter(|gui_edge| gui_edge.output_slot.node_id == node_id)
+        .map(|gui_edge| gui_edge.input_slot.node_id)
+        .collect::<Vec<NodeId>>();
+    affected.push(node_id);
+
+    let mut q_node = world.query::<(&NodeIdComponent, &mut ThumbnailState)>();
+    for (_, mut thumb_state) in q_node
+        .iter_mut(world)
+        .filter(|(node_id_iter, _)| affected.contains(&node_id_iter.0))
+    {
+        if *thumb_state == ThumbnailState::Present {
+            *thumb_state = ThumbnailState::Missing;
+        }
+    }
+}
@@ -0,0 +1,115 @@
+/// A dismissible list of errors that don't belong to a specific node, like failed exports.
+use bevy::prelude::*;
+
+use crate::{
+    inspector::PANEL_WIDTH,
+    scan_code_input::{ScanCode, ScanCodeInput},
+    AmbiguitySet, CustomStage, ToolState,
+};
+
+const NOTIFICATION_COLOR: Color = Color::rgb(1.0, 0.45, 0.4);
+
+#[derive(Debug, Default)]
+pub(crate) struct Notifications(Vec<String>);
+
+impl std::ops::Deref for Notifications {
+    type Target = Vec<String>;
+    fn deref(&self) -> &Vec<String> {
+        &self.0
+    }
+}
+
+impl Notifications {
+    /// Shows an error to the user, and logs it.
+    pub fn error(&mut self, message: impl Into<String>) {
+        let message = message.into();
+        error!("{}", message);
+        self.0.push(message);
+    }
+
+    pub fn clear(&mut self) {
+        self.0.clear();
+    }
+}
+
+#[derive(Component)]
+struct NotificationMarker;
+
+pub(crate) struct NotificationPlugin;
+
+impl Plugin for NotificationPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Notifications::default())
+            .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Update)
+                    .after(CustomStage::Setup)
+                    .with_system(
+                        dismiss
+                            .system()
+                            .with_run_criteria(State::on_update(ToolState::None))
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            )
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Apply)
+                    .after(CustomStage::Update)
+                    .with_system(update_notifications.system().in_ambiguity_set(AmbiguitySet)),
+            );
+    }
+}
+
+fn setup(mut commands: Commands, asset_server: Res<AssetServer>) {
+    commands
+        .spawn_bundle(TextBundle {
+            style: Style {
+                position_type: PositionType::Absolute,
+                position: Rect {
+                    top: Val::Px(10.0),
+                    right: Val::Px(PANEL_WIDTH + 10.0),
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            text: Text::with_section(
+                String::new(),
+                TextStyle {
+                    font: asset_server.load("fonts/FiraSans-Regular.ttf"),
+                    font_size: 20.0,
+                    color: NOTIFICATION_COLOR,
+                },
+                TextAlignment {
+                    horizontal: HorizontalAlign::Right,
+                    vertical: VerticalAlign::Top,
+                },
+            ),
+            ..Default::default()
+        })
+        .insert(NotificationMarker);
+}
+
+fn dismiss(mut scan_code_input: ResMut<ScanCodeInput>, mut notifications: ResMut<Notifications>) {
+    if !notifications.is_empty() && scan_code_input.just_pressed(ScanCode::Escape) {
+        scan_code_input.clear_just_pressed(ScanCode::Escape);
+        notifications.clear();
+    }
+}
+
+fn update_notifications(
+    notifications: Res<Notifications>,
+    mut q_text: Query<&mut Text, With<NotificationMarker>>,
+) {
+    if notifications.is_changed() {
+        if let Ok(mut text) = q_text.get_single_mut() {
+            text.sections[0].value = if notifications.is_empty() {
+                String::new()
+            } else {
+                format!("{}\nEsc: Dismiss", notifications.join("\n"))
+            };
+        }
+    }
+}
@@ -0,0 +1,420 @@
+/// Channel packing presets, which combine grayscale outputs into the packed textures that game
+/// engines expect, like Unreal's ORM textures.
+///
+/// The packed textures are assembled when exporting, so no extra nodes are needed. Presets can be
+/// added, or the built in ones overridden, in `packing_presets.ron` in the user's config
+/// directory, for instance `~/.config/vismut/packing_presets.ron` on Linux.
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use vismut_core::{
+    live_graph::LiveGraph,
+    node::node_type::NodeType,
+    node_graph::{NodeId, SlotId},
+    slot_data::{ChannelPixel, Size as CoreSize},
+};
+
+use crate::{
+    export_format::{pixels, ExportFormat, Pixels},
+    export_outputs::Exporter,
+    export_template::expand,
+    instruction::*,
+    keymap::{Action, Keymap},
+    scan_code_input::{ScanCode, ScanCodeInput},
+    undo::prelude::*,
+    AmbiguitySet, CustomStage, ToolState,
+};
+
+const PRESETS_FILE: &str = "packing_presets.ron";
+
+fn is_false(value: &bool) -> bool {
+    !value
+}
+
+/// Where the values of a channel in a packed texture come from.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct ChannelSource {
+    /// The name of a grayscale output node.
+    pub output: String,
+    /// Use one minus the value, for instance to get smoothness from roughness.
+    #[serde(default, skip_serializing_if = "is_false")]
+    pub invert: bool,
+}
+
+impl ChannelSource {
+    fn new(output: &str) -> Option<Self> {
+        Some(Self {
+            output: output.to_string(),
+            invert: false,
+        })
+    }
+
+    fn inverted(output: &str) -> Option<Self> {
+        Some(Self {
+            output: output.to_string(),
+            invert: true,
+        })
+    }
+}
+
+/// A texture that is assembled from grayscale outputs. Channels without a source are black, and
+/// the texture has no alpha channel if `a` doesn't have a source.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct PackedTexture {
+    /// Can contain the same tokens as the export name template, except `{output}`. The result is
+    /// used as `{output}` in the export name template.
+    pub name: String,
+    #[serde(default)]
+    pub format: ExportFormat,
+    #[serde(default)]
+    pub r: Option<ChannelSource>,
+    #[serde(default)]
+    pub g: Option<ChannelSource>,
+    #[serde(default)]
+    pub b: Option<ChannelSource>,
+    #[serde(default)]
+    pub a: Option<ChannelSource>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub(crate) struct PackingPreset {
+    pub name: String,
+    pub textures: Vec<PackedTexture>,
+}
+
+/// All available presets, the built in ones first.
+#[derive(Debug)]
+pub(crate) struct PackingPresets(Vec<PackingPreset>);
+
+impl std::ops::Deref for PackingPresets {
+    type Target = Vec<PackingPreset>;
+    fn deref(&self) -> &Vec<PackingPreset> {
+        &self.0
+    }
+}
+
+impl Default for PackingPresets {
+    fn default() -> Self {
+        Self(vec![
+            PackingPreset {
+                name: "Unreal ORM".to_string(),
+                textures: vec![PackedTexture {
+                    name: "T_{project}_ORM".to_string(),
+                    format: ExportFormat::Png8,
+                    r: ChannelSource::new("AO"),
+                    g: ChannelSource::new("Roughness"),
+                    b: ChannelSource::new("Metallic"),
+                    a: None,
+                }],
+            },
+            PackingPreset {
+                name: "MRA".to_string(),
+                textures: vec![PackedTexture {
+                    name: "{project}_MRA".to_string(),
+                    format: ExportFormat::Png8,
+                    r: ChannelSource::new("Metallic"),
+                    g: ChannelSource::new("Roughness"),
+                    b: ChannelSource::new("AO"),
+                    a: None,
+                }],
+            },
+            PackingPreset {
+                name: "Unity HDRP Mask Map".to_string(),
+                textures: vec![PackedTexture {
+                    name: "{project}_MaskMap".to_string(),
+                    format: ExportFormat::Png8,
+                    r: ChannelSource::new("Metallic"),
+                    g: ChannelSource::new("AO"),
+                    b: None,
+                    a: ChannelSource::inverted("Roughness"),
+                }],
+            },
+            PackingPreset {
+                name: "Unity URP Metallic Smoothness".to_string(),
+                textures: vec![PackedTexture {
+                    name: "{project}_MetallicSmoothness".to_string(),
+                    format: ExportFormat::Png8,
+                    r: ChannelSource::new("Metallic"),
+                    g: ChannelSource::new("Metallic"),
+                    b: ChannelSource::new("Metallic"),
+                    a: ChannelSource::inverted("Roughness"),
+                }],
+            },
+        ])
+    }
+}
+
+impl PackingPresets {
+    /// The selected preset, if one is selected. Fails if there is no preset with the selected
+    /// name, for instance when a project uses a preset from another computer.
+    pub fn selected(&self, selected: &SelectedPreset) -> Result<Option<&PackingPreset>> {
+        match &selected.0 {
+            Some(name) => self
+                .iter()
+                .find(|preset| &preset.name == name)
+                .map(Some)
+                .ok_or_else(|| anyhow!("there is no channel packing preset named {:?}", name)),
+            None => Ok(None),
+        }
+    }
+
+    fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("vismut").join(PRESETS_FILE))
+    }
+
+    /// The built in presets, along with the ones in the user's config directory. A user preset
+    /// replaces the built in one with the same name.
+    pub fn load() -> Self {
+        let mut presets = Self::default();
+
+        let path = match Self::path() {
+            Some(path) if path.exists() => path,
+            _ => return presets,
+        };
+
+        match read_presets(&path) {
+            Ok(user_presets) => {
+                info!("Loaded packing presets from {:?}", path);
+                for user_preset in user_presets {
+                    match presets
+                        .0
+                        .iter_mut()
+                        .find(|preset| preset.name == user_preset.name)
+                    {
+                        Some(preset) => *preset = user_preset,
+                        None => presets.0.push(user_preset),
+                    }
+                }
+            }
+            Err(e) => error!("Unable to read the packing presets {:?}: {}", path, e),
+        }
+
+        presets
+    }
+}
+
+fn read_presets(path: &Path) -> Result<Vec<PackingPreset>> {
+    Ok(ron::de::from_str(&fs::read_to_string(path)?)?)
+}
+
+/// The name of the preset that is used when exporting the outputs of the project.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub(crate) struct SelectedPreset(pub Option<String>);
+
+/// Finds the grayscale output each channel of a texture comes from, and waits for them to be
+/// processed. Returns the nodes along with whether they are inverted, and the size they share.
+fn texture_sources(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    texture: &PackedTexture,
+) -> Result<(Vec<Option<(NodeId, bool)>>, CoreSize)> {
+    let sources = [&texture.r, &texture.g, &texture.b, &texture.a];
+
+    let mut size = None;
+    let mut node_ids = Vec::new();
+    for source in sources.iter() {
+        let source = match source {
+            Some(source) => source,
+            None => {
+                node_ids.push(None);
+                continue;
+            }
+        };
+
+        let node_id = {
+            let live_graph = live_graph.read().map_err(|e| anyhow!("{}", e))?;
+            live_graph
+                .output_ids()
+                .into_iter()
+                .find(|node_id| {
+                    matches!(
+                        live_graph.node(*node_id).map(|node| node.node_type),
+                        Ok(NodeType::OutputGray(name)) if name == source.output
+                    )
+                })
+                .ok_or_else(|| anyhow!("there is no grayscale output named {:?}", source.output))?
+        };
+
+        let live_graph = LiveGraph::await_clean_read(live_graph, node_id)?;
+        let source_size = live_graph.slot_data_size(node_id, SlotId(0))?;
+        match size {
+            Some(size) if (size.width, size.height) != (source_size.width, source_size.height) => {
+                bail!(
+                    "{:?} is {}x{}, but the other channels are {}x{}",
+                    source.output,
+                    source_size.width,
+                    source_size.height,
+                    size.width,
+                    size.height
+                )
+            }
+            _ => size = Some(source_size),
+        }
+
+        node_ids.push(Some((node_id, source.invert)));
+    }
+
+    let size = size.ok_or_else(|| anyhow!("none of the channels have a source"))?;
+    Ok((node_ids, size))
+}
+
+/// The path a packed texture is written to.
+pub(crate) fn packed_path(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    exporter: &Exporter,
+    texture: &PackedTexture,
+) -> Result<PathBuf> {
+    let (_, size) = texture_sources(live_graph, texture)?;
+    let name = expand(&texture.name, exporter.settings.project, "", size)?;
+    exporter.path(&name, texture.format, size)
+}
+
+/// Assembles a packed texture from the grayscale outputs it names, and writes it to `path`.
+pub(crate) fn pack_texture(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    exporter: &Exporter,
+    texture: &PackedTexture,
+    path: &Path,
+) -> Result<()> {
+    let (node_ids, size) = texture_sources(live_graph, texture)?;
+
+    let mut channels: Vec<Option<Vec<ChannelPixel>>> = Vec::new();
+    for source in node_ids {
+        let (node_id, invert) = match source {
+            Some(source) => source,
+            None => {
+                channels.push(None);
+                continue;
+            }
+        };
+
+        let live_graph = LiveGraph::await_clean_read(live_graph, node_id)?;
+        let mut channel = match pixels(&live_graph, node_id)? {
+            Pixels::Gray(channel) => channel,
+            _ => bail!("output {} is not a grayscale output", node_id),
+        };
+        if invert {
+            for value in channel.iter_mut() {
+                *value = 1.0 - *value;
+            }
+        }
+
+        channels.push(Some(channel));
+    }
+
+    let pixel_count = (size.width * size.height) as usize;
+    let has_alpha = texture.a.is_some();
+    let channel_count = if has_alpha { 4 } else { 3 };
+
+    let mut packed = Vec::with_capacity(pixel_count * channel_count);
+    for i in 0..pixel_count {
+        for channel in channels.iter().take(channel_count) {
+            packed.push(channel.as_ref().map_or(0.0, |channel| channel[i]));
+        }
+    }
+    let packed = if has_alpha {
+        Pixels::Rgba(packed)
+    } else {
+        Pixels::Rgb(packed)
+    };
+
+    exporter.write(path, texture.format, size, packed)
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct SetSelectedPreset {
+    pub from: SelectedPreset,
+    pub to: SelectedPreset,
+}
+impl UndoCommand for SetSelectedPreset {
+    fn forward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        world.insert_resource(self.to.clone());
+    }
+
+    fn backward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        world.insert_resource(self.from.clone());
+    }
+}
+
+pub(crate) struct PackingPlugin;
+
+impl Plugin for PackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PackingPresets::load())
+            .insert_resource(SelectedPreset::default())
+            .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Update)
+                    .after(CustomStage::Setup)
+                    .with_system(
+                        choose_preset_enter
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::PackingPreset))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        choose_preset_update
+                            .system()
+                            .with_run_criteria(State::on_update(ToolState::PackingPreset))
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            );
+    }
+}
+
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::PackingPreset));
+}
+
+fn choose_preset_enter(
+    mut instructions: ResMut<Instructions>,
+    packing_presets: Res<PackingPresets>,
+    selected_preset: Res<SelectedPreset>,
+) {
+    let mut output = format!(
+        "## Channel packing preset ({})\n0: None\n",
+        selected_preset.0.as_deref().unwrap_or("None")
+    );
+    for (i, preset) in packing_presets.iter().enumerate() {
+        output = format!("{}{}: {}\n", output, i + 1, preset.name);
+    }
+
+    instructions.insert(InstructId::Tool, output);
+}
+
+fn choose_preset_update(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut scan_code_input: ResMut<ScanCodeInput>,
+    packing_presets: Res<PackingPresets>,
+    selected_preset: Res<SelectedPreset>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+) {
+    let scan_codes: Vec<ScanCode> = scan_code_input.get_just_pressed().copied().collect();
+
+    for scan_code in scan_codes {
+        let to = match scan_code.to_usize() {
+            Some(0) => SelectedPreset(None),
+            Some(i) => match packing_presets.get(i - 1) {
+                Some(preset) => SelectedPreset(Some(preset.name.clone())),
+                None => continue,
+            },
+            None => continue,
+        };
+
+        scan_code_input.clear_just_pressed(scan_code);
+        undo_command_manager.push(Box::new(SetSelectedPreset {
+            from: selected_preset.clone(),
+            to,
+        }));
+        undo_command_manager.push(Box::new(Checkpoint));
+        tool_state.overwrite_replace(ToolState::None).unwrap();
+        break;
+    }
+}
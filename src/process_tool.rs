@@ -0,0 +1,234 @@
+/// Switching between automatic and manual processing, and processing the graph on demand.
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
+
+use bevy::prelude::*;
+use vismut_core::{
+    edge::Edge,
+    live_graph::{LiveGraph, NodeState},
+    node_graph::NodeId,
+};
+
+use crate::{
+    core_translation::reprocess,
+    instruction::*,
+    keymap::{Action, Keymap},
+    node_error::ProcessingFailed,
+    shared::NodeIdComponent,
+    sync_graph::Edge as GuiEdge,
+    AmbiguitySet, CustomStage, ToolState,
+};
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum ProcessingMode {
+    /// Nodes are processed as soon as they change.
+    Auto,
+    /// Nodes are only processed when the user asks for it.
+    Manual,
+}
+
+impl Default for ProcessingMode {
+    fn default() -> Self {
+        Self::Auto
+    }
+}
+
+/// A processing pass requested with `Action::Process`. In manual mode, processing is turned off
+/// again when the pass is done.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum ProcessPass {
+    None,
+    /// Nodes are being processed.
+    Running,
+}
+
+impl Default for ProcessPass {
+    fn default() -> Self {
+        Self::None
+    }
+}
+
+pub(crate) struct ProcessToolPlugin;
+
+impl Plugin for ProcessToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ProcessingMode::default())
+            .insert_resource(ProcessPass::default())
+            .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Update)
+                    .after(CustomStage::Setup)
+                    .with_system(
+                        process
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::Process))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        toggle_processing_mode
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::ProcessingMode))
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            )
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Apply)
+                    .after(CustomStage::Update)
+                    .with_system(
+                        manual_processing
+                            .system()
+                            .chain(processing_instructions.system())
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            );
+    }
+}
+
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Process));
+    tool_list.insert(keymap.help(Action::ProcessingMode));
+}
+
+/// Makes every node dirty and starts processing, so the whole graph is computed again.
+///
+/// Nodes that are already dirty are left alone. They are processed anyway, and a node that failed
+/// to process keeps its failure until it or a node it depends on changes, so the pass doesn't
+/// wait on a node that would only fail again.
+fn process(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut process_pass: ResMut<ProcessPass>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    q_node: Query<&NodeIdComponent>,
+) {
+    let mut live_graph = live_graph.write().unwrap();
+    for node_id in q_node.iter() {
+        if live_graph.node_state(node_id.0).ok() != Some(NodeState::Clean) {
+            continue;
+        }
+        if let Err(e) = reprocess(&mut live_graph, node_id.0) {
+            warn!("Unable to process {}: {}", node_id.0, e);
+        }
+    }
+
+    *process_pass = ProcessPass::Running;
+    live_graph.auto_update = true;
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+fn toggle_processing_mode(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut processing_mode: ResMut<ProcessingMode>,
+    process_pass: Res<ProcessPass>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+) {
+    *processing_mode = match *processing_mode {
+        ProcessingMode::Auto => ProcessingMode::Manual,
+        ProcessingMode::Manual => ProcessingMode::Auto,
+    };
+    live_graph.write().unwrap().auto_update =
+        *processing_mode == ProcessingMode::Auto || *process_pass != ProcessPass::None;
+
+    info!("Processing mode set to {:?}", *processing_mode);
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+/// Ends a requested processing pass once none of the nodes that are not clean can still become
+/// clean, and stops processing in manual mode. A node that failed to process stays dirty, and so
+/// do the nodes that depend on it, so the pass doesn't wait for every node to be clean. Returns
+/// the number of nodes that are not clean.
+fn manual_processing(
+    processing_mode: Res<ProcessingMode>,
+    mut process_pass: ResMut<ProcessPass>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    q_node: Query<(&NodeIdComponent, Option<&ProcessingFailed>)>,
+    q_edge: Query<&GuiEdge>,
+) -> usize {
+    let (stale_count, in_progress) = {
+        let live_graph = live_graph.read().unwrap();
+        let states: HashMap<NodeId, (NodeState, bool)> = q_node
+            .iter()
+            .filter_map(|(node_id, failed)| {
+                live_graph
+                    .node_state(node_id.0)
+                    .ok()
+                    .map(|state| (node_id.0, (state, failed.is_some())))
+            })
+            .collect();
+        let edges: Vec<Edge> = q_edge
+            .iter()
+            .map(|gui_edge| Edge::from(*gui_edge))
+            .collect();
+
+        let stale: Vec<NodeId> = states
+            .iter()
+            .filter(|(_, (state, _))| *state != NodeState::Clean)
+            .map(|(node_id, _)| *node_id)
+            .collect();
+
+        (
+            stale.len(),
+            stale
+                .iter()
+                .any(|node_id| !stuck(*node_id, &states, &edges)),
+        )
+    };
+
+    if *process_pass == ProcessPass::Running && !in_progress {
+        if *processing_mode == ProcessingMode::Manual {
+            live_graph.write().unwrap().auto_update = false;
+        }
+        *process_pass = ProcessPass::None;
+    }
+
+    stale_count
+}
+
+/// Returns true if processing can't make the node clean, because it's dirty and it or a node it
+/// depends on failed to process. Nodes that are being processed, or are waiting for their inputs
+/// to be, are not stuck.
+fn stuck(node_id: NodeId, states: &HashMap<NodeId, (NodeState, bool)>, edges: &[Edge]) -> bool {
+    match states.get(&node_id) {
+        Some((NodeState::Dirty, failed)) => {
+            *failed
+                || edges
+                    .iter()
+                    .filter(|edge| edge.input_id == node_id)
+                    .any(|edge| stuck(edge.output_id, states, edges))
+        }
+        _ => false,
+    }
+}
+
+fn processing_instructions(
+    In(stale_count): In<usize>,
+    processing_mode: Res<ProcessingMode>,
+    process_pass: Res<ProcessPass>,
+    mut instructions: ResMut<Instructions>,
+) {
+    let text = match *processing_mode {
+        ProcessingMode::Auto => "\nProcessing: Auto".to_string(),
+        ProcessingMode::Manual => {
+            if *process_pass != ProcessPass::None {
+                format!("\nProcessing: Manual - Processing {} node(s)", stale_count)
+            } else if stale_count > 0 {
+                format!(
+                    "\nProcessing: Manual - {} stale node(s), press F12 to process",
+                    stale_count
+                )
+            } else {
+                "\nProcessing: Manual".to_string()
+            }
+        }
+    };
+
+    if instructions.get(&InstructId::Processing) != Some(&text) {
+        instructions.insert(InstructId::Processing, text);
+    }
+}
@@ -0,0 +1,783 @@
+/// Saving and loading projects.
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{BufReader, BufWriter, Write},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::*;
+use native_dialog::FileDialog;
+use serde::{Deserialize, Serialize};
+use vismut_core::{
+    edge::Edge,
+    live_graph::LiveGraph,
+    node::{node_type::NodeType, Node},
+    node_graph::{NodeGraph, NodeId},
+    texture_processor::TextureProcessor,
+};
+
+use crate::{
+    drag_drop::node::update_node_gui_edges,
+    embed::{EmbeddedImage, EmbeddedImages},
+    export_format::{ExportFormat, ExportFormats},
+    export_template::{ExportTemplate, DEFAULT_TEMPLATE, UNTITLED_PROJECT},
+    group::{GroupContents, GroupStack},
+    instruction::ToolList,
+    keymap::{Action, Keymap},
+    normal_map::{NormalMap, NormalMaps},
+    notification::Notifications,
+    packing::SelectedPreset,
+    scan_code_input::ScanCodeInput,
+    shared::NodeIdComponent,
+    sync_graph::{remove_gui_node, spawn_gui_node_2, Edge as GuiEdge, InputCounts},
+    thumbnail::ThumbnailExposures,
+    undo::{edge::AddEdgeOnly, prelude::*},
+    AmbiguitySet, CustomStage, ToolState,
+};
+
+pub(crate) const PROJECT_EXTENSION: &str = "vismut";
+/// Increase this whenever the project format changes in a way older versions can't read.
+const PROJECT_VERSION: u32 = 2;
+
+/// The path of the currently open project, if it has been saved or opened.
+#[derive(Debug, Default)]
+pub(crate) struct ProjectPath(pub Option<PathBuf>);
+
+impl ProjectPath {
+    /// The file name of the project without its extension, used when naming exports.
+    pub fn name(&self) -> String {
+        self.0
+            .as_ref()
+            .and_then(|path| path.file_stem())
+            .map(|stem| stem.to_string_lossy().into_owned())
+            .unwrap_or_else(|| UNTITLED_PROJECT.to_string())
+    }
+
+    /// Resolves a path the user typed. Relative paths are relative to the folder of the project
+    /// file, the same as in saved projects, so they can't be used before the project is saved.
+    pub fn resolve(&self, path: &Path) -> Result<PathBuf> {
+        if path.is_absolute() {
+            return Ok(path.to_path_buf());
+        }
+
+        match self.0.as_ref().and_then(|project| project.parent()) {
+            Some(dir) => Ok(normalize(&dir.join(path))),
+            None => bail!("save the project before using a relative path, or use a full path"),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct ProjectNode {
+    pub node: Node,
+    pub translation: [f32; 2],
+    /// Only set for nodes with a variable number of inputs.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub input_count: Option<usize>,
+    /// Only set for output nodes that don't use the default export format.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_format: Option<ExportFormat>,
+    /// Only set for nodes with an embedded image.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedded_image: Option<EmbeddedImage>,
+    /// Only set for nodes whose thumbnail has an exposure.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub thumbnail_exposure: Option<f32>,
+    /// Only set for Height to Normal nodes whose settings have been changed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub normal_map: Option<NormalMap>,
+    /// Only set for `Graph` nodes, the nodes in the group with their positions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub group: Option<Box<Project>>,
+}
+
+impl ProjectNode {
+    /// Returns the node. The `NodeGraph` of a `Graph` node is built again from `group`.
+    ///
+    /// Embedded images have to be in the `LiveGraph` already, see `Project::embed_images`.
+    pub(crate) fn node(&self, live_graph: &mut LiveGraph) -> Result<Node> {
+        let mut node = self.node.clone();
+        if let Some(group) = &self.group {
+            node.node_type = NodeType::Graph(group.node_graph(live_graph)?);
+        }
+        Ok(node)
+    }
+}
+
+/// Everything that is needed to restore a workspace.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Project {
+    pub version: u32,
+    pub nodes: Vec<ProjectNode>,
+    pub edges: Vec<Edge>,
+    /// Only set if it's not the default template.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub export_template: Option<String>,
+    /// The name of the channel packing preset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub packing_preset: Option<String>,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self {
+            version: PROJECT_VERSION,
+            nodes: Vec::new(),
+            edges: Vec::new(),
+            export_template: None,
+            packing_preset: None,
+        }
+    }
+}
+
+impl Project {
+    /// Gathers the given nodes from the `LiveGraph`, along with their positions in the workspace.
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        live_graph: &LiveGraph,
+        input_counts: &InputCounts,
+        export_formats: &ExportFormats,
+        embedded_images: &EmbeddedImages,
+        thumbnail_exposures: &ThumbnailExposures,
+        normal_maps: &NormalMaps,
+        group_contents: &GroupContents,
+        nodes: impl Iterator<Item = (NodeId, Vec2)>,
+        edges: Vec<Edge>,
+    ) -> Result<Self> {
+        let nodes = nodes
+            .map(|(node_id, translation)| {
+                let mut node = live_graph.node(node_id)?;
+                let group = group_contents.get(&node_id).cloned().map(Box::new);
+                if group.is_some() {
+                    // The graph is built again from `group` when it's loaded, so there is no need
+                    // to store it twice.
+                    node.node_type = NodeType::Graph(NodeGraph::new());
+                }
+                let embedded_image = match node.node_type {
+                    NodeType::Embed(embedded_slot_data_id) => Some(EmbeddedImage::encode(
+                        embedded_images
+                            .get(&embedded_slot_data_id)
+                            .ok_or_else(|| anyhow!("an embedded image is missing"))?,
+                    )?),
+                    _ => None,
+                };
+
+                Ok(ProjectNode {
+                    node,
+                    translation: translation.into(),
+                    input_count: input_counts.get(&node_id).copied(),
+                    export_format: export_formats.get(&node_id).copied(),
+                    embedded_image,
+                    thumbnail_exposure: thumbnail_exposures.get(&node_id).copied(),
+                    normal_map: normal_maps.get(&node_id).copied(),
+                    group,
+                })
+            })
+            .collect::<Result<Vec<ProjectNode>>>()?;
+
+        Ok(Self {
+            nodes,
+            edges,
+            ..Default::default()
+        })
+    }
+
+    pub(crate) fn export_template(&self) -> ExportTemplate {
+        self.export_template
+            .clone()
+            .map(ExportTemplate)
+            .unwrap_or_default()
+    }
+
+    /// The export formats of the nodes that have one.
+    pub(crate) fn export_formats(&self) -> ExportFormats {
+        ExportFormats(
+            self.nodes
+                .iter()
+                .filter_map(|project_node| {
+                    project_node
+                        .export_format
+                        .map(|export_format| (project_node.node.node_id, export_format))
+                })
+                .collect(),
+        )
+    }
+
+    /// The thumbnail exposures of the nodes that have one.
+    pub(crate) fn thumbnail_exposures(&self) -> ThumbnailExposures {
+        ThumbnailExposures(
+            self.nodes
+                .iter()
+                .filter_map(|project_node| {
+                    project_node
+                        .thumbnail_exposure
+                        .map(|exposure| (project_node.node.node_id, exposure))
+                })
+                .collect(),
+        )
+    }
+
+    /// The normal map settings of the nodes that have them.
+    pub(crate) fn normal_maps(&self) -> NormalMaps {
+        NormalMaps(
+            self.nodes
+                .iter()
+                .filter_map(|project_node| {
+                    project_node
+                        .normal_map
+                        .map(|normal_map| (project_node.node.node_id, normal_map))
+                })
+                .collect(),
+        )
+    }
+
+    /// The groups of the `Graph` nodes that have one.
+    pub(crate) fn group_contents(&self) -> GroupContents {
+        GroupContents(
+            self.nodes
+                .iter()
+                .filter_map(|project_node| {
+                    project_node
+                        .group
+                        .as_ref()
+                        .map(|group| (project_node.node.node_id, (**group).clone()))
+                })
+                .collect(),
+        )
+    }
+
+    /// Reads a project file. Relative image paths are resolved against the folder of the file.
+    pub fn read(path: &Path) -> Result<Self> {
+        let reader = BufReader::new(File::open(path)?);
+        let mut project = Self::check(serde_json::from_reader(reader)?)?;
+
+        if let Some(dir) = path.parent() {
+            project.map_image_paths(&|image_path| normalize(&dir.join(image_path)));
+        }
+
+        Ok(project)
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        Self::check(serde_json::from_str(json)?)
+    }
+
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(self)?)
+    }
+
+    /// Checks that the project can be loaded by this version of Vismut.
+    fn check(project: Self) -> Result<Self> {
+        if project.version > PROJECT_VERSION {
+            bail!(
+                "the project was saved with a newer version of Vismut (format version {}, \
+                this version supports up to {})",
+                project.version,
+                PROJECT_VERSION
+            );
+        }
+        project.check_ids()?;
+
+        Ok(project)
+    }
+
+    /// Checks that no two nodes in the same group level have the same id, and that the edges
+    /// only connect nodes that exist. The nodes are added to the graph with the ids they have in
+    /// the file, so a broken file would otherwise fail halfway through loading.
+    fn check_ids(&self) -> Result<()> {
+        let mut node_ids = HashSet::new();
+        for project_node in &self.nodes {
+            let node_id = project_node.node.node_id;
+            if !node_ids.insert(node_id) {
+                bail!("more than one node has the id {}", node_id);
+            }
+            if let Some(group) = &project_node.group {
+                group.check_ids()?;
+            }
+        }
+
+        for edge in &self.edges {
+            for node_id in [edge.output_id, edge.input_id] {
+                if !node_ids.contains(&node_id) {
+                    bail!(
+                        "an edge is connected to the node {}, which doesn't exist",
+                        node_id
+                    );
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the project to a file, with image paths relative to the folder of the file so the
+    /// project can be moved along with its images.
+    ///
+    /// The project is written to a temporary file next to `path` first, which then replaces the
+    /// file. That way a failed save leaves the previous save as it was.
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut project = self.clone();
+        if let Some(dir) = path.parent() {
+            project.map_image_paths(&|image_path| relative_path(image_path, dir));
+        }
+
+        let mut temp_name = path
+            .file_name()
+            .ok_or_else(|| anyhow!("the path doesn't have a file name"))?
+            .to_os_string();
+        temp_name.push(".tmp");
+        let temp_path = path.with_file_name(temp_name);
+
+        let result = (|| -> Result<()> {
+            let mut writer = BufWriter::new(File::create(&temp_path)?);
+            serde_json::to_writer_pretty(&mut writer, &project)?;
+            writer.flush()?;
+            writer.get_ref().sync_all()?;
+            fs::rename(&temp_path, path)?;
+            Ok(())
+        })();
+        if result.is_err() {
+            let _ = fs::remove_file(&temp_path);
+        }
+        result
+    }
+
+    fn map_image_paths(&mut self, f: &dyn Fn(&Path) -> PathBuf) {
+        for project_node in &mut self.nodes {
+            if let NodeType::Image(path) = &project_node.node.node_type {
+                project_node.node.node_type = NodeType::Image(f(path));
+            }
+            if let Some(group) = &mut project_node.group {
+                group.map_image_paths(f);
+            }
+        }
+    }
+
+    /// Adds the embedded images of the project to the `LiveGraph`, including the ones in groups,
+    /// and points the nodes at them. Each image gets a new id, so it doesn't clash with images
+    /// that are already in the graph.
+    ///
+    /// This is done once for a project that comes from a file or the clipboard. The ids are kept
+    /// after that, so commands in the undo history of each group level keep referring to the
+    /// right images when entering and leaving groups.
+    pub(crate) fn embed_images(
+        &mut self,
+        live_graph: &mut LiveGraph,
+        embedded_images: &mut EmbeddedImages,
+    ) -> Result<()> {
+        for project_node in &mut self.nodes {
+            if let Some(embedded_image) = &project_node.embedded_image {
+                project_node.node.node_type = embedded_image.embed(live_graph, embedded_images)?;
+            }
+            if let Some(group) = &mut project_node.group {
+                group.embed_images(live_graph, embedded_images)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Builds a `LiveGraph` from the project without any GUI, and adds it to the
+    /// `TextureProcessor` so it starts processing.
+    pub fn live_graph(&self, tex_pro: &Arc<TextureProcessor>) -> Result<Arc<RwLock<LiveGraph>>> {
+        let mut live_graph = LiveGraph::new(Arc::clone(&tex_pro.add_buffer_queue));
+        live_graph.auto_update = true;
+
+        let mut project = self.clone();
+        project.embed_images(&mut live_graph, &mut EmbeddedImages::default())?;
+        for project_node in &project.nodes {
+            let node = project_node.node(&mut live_graph)?;
+            live_graph.add_node_with_id(node)?;
+        }
+        for edge in &self.edges {
+            live_graph.connect(
+                edge.output_id,
+                edge.input_id,
+                edge.output_slot,
+                edge.input_slot,
+            )?;
+        }
+
+        let live_graph = Arc::new(RwLock::new(live_graph));
+        tex_pro.push_live_graph(Arc::clone(&live_graph))?;
+
+        Ok(live_graph)
+    }
+
+    /// Builds the `NodeGraph` of a `Graph` node from the project of its group. The core looks for
+    /// embedded images in `live_graph`, in nested graphs as well.
+    pub(crate) fn node_graph(&self, live_graph: &mut LiveGraph) -> Result<NodeGraph> {
+        let mut node_graph = NodeGraph::new();
+
+        for project_node in &self.nodes {
+            let node = project_node.node(live_graph)?;
+            node_graph.add_node_with_id(node)?;
+        }
+        for edge in &self.edges {
+            node_graph.connect(
+                edge.output_id,
+                edge.input_id,
+                edge.output_slot,
+                edge.input_slot,
+            )?;
+        }
+
+        Ok(node_graph)
+    }
+}
+
+/// Returns `path` relative to the `base` folder, with `/` as the separator so it works on all
+/// platforms. Returns `path` unchanged if it can't be made relative, for instance if it's on
+/// another drive.
+fn relative_path(path: &Path, base: &Path) -> PathBuf {
+    if !path.is_absolute() || !base.is_absolute() {
+        return path.to_path_buf();
+    }
+
+    let mut path_components = path.components().peekable();
+    let mut base_components = base.components().peekable();
+    if path_components.peek() != base_components.peek() {
+        return path.to_path_buf();
+    }
+
+    while let (Some(path_component), Some(base_component)) =
+        (path_components.peek(), base_components.peek())
+    {
+        if path_component != base_component {
+            break;
+        }
+        path_components.next();
+        base_components.next();
+    }
+
+    let relative: Vec<String> = base_components
+        .map(|_| "..".to_string())
+        .chain(
+            path_components.map(|component| component.as_os_str().to_string_lossy().into_owned()),
+        )
+        .collect();
+    PathBuf::from(relative.join("/"))
+}
+
+/// Removes `.` and `..` from a path without touching the file system.
+fn normalize(path: &Path) -> PathBuf {
+    let mut normalized = PathBuf::new();
+
+    for component in path.components() {
+        match component {
+            Component::CurDir => (),
+            Component::ParentDir => {
+                if matches!(normalized.components().last(), Some(Component::Normal(_))) {
+                    normalized.pop();
+                } else {
+                    normalized.push(component);
+                }
+            }
+            _ => normalized.push(component),
+        }
+    }
+
+    normalized
+}
+
+/// Replaces everything in the workspace with the contents of a `Project`. This clears the undo
+/// history, since none of it applies to the loaded project.
+#[derive(Debug)]
+pub struct LoadProject(pub Project);
+impl UndoCommand for LoadProject {
+    fn command_type(&self) -> UndoCommandType {
+        UndoCommandType::Custom
+    }
+
+    fn forward(&self, world: &mut World, undo_command_manager: &mut UndoCommandManager) {
+        let live_graph = Arc::clone(world.get_resource::<Arc<RwLock<LiveGraph>>>().unwrap());
+        let mut project = self.0.clone();
+        let mut embedded_images = EmbeddedImages::default();
+        let result = project.embed_images(&mut live_graph.write().unwrap(), &mut embedded_images);
+        if let Err(e) = result {
+            error!("Unable to load an embedded image: {}", e);
+            return;
+        }
+        world.insert_resource(embedded_images);
+
+        load_nodes(world, &project, undo_command_manager);
+
+        world.insert_resource(self.0.export_template());
+        world.insert_resource(SelectedPreset(self.0.packing_preset.clone()));
+        world.insert_resource(GroupStack::default());
+
+        undo_command_manager.command_batch.clear();
+        undo_command_manager.undo_stack.clear();
+        undo_command_manager.redo_stack.clear();
+    }
+
+    fn backward(&self, _: &mut World, _: &mut UndoCommandManager) {
+        unreachable!("this command is never put on the undo stack");
+    }
+}
+
+/// Replaces the nodes and edges in the workspace with the ones in the project, along with what's
+/// kept about each node outside of the `LiveGraph`. Settings that belong to the whole project are
+/// left alone, so this is also used when entering and leaving groups.
+///
+/// The embedded images of the project have to be in `EmbeddedImages` and the `LiveGraph` already.
+/// They are shared by all group levels, so they are not replaced here.
+pub(crate) fn load_nodes(
+    world: &mut World,
+    project: &Project,
+    undo_command_manager: &mut UndoCommandManager,
+) {
+    clear_workspace(world);
+
+    if let Some(mut input_counts) = world.get_resource_mut::<InputCounts>() {
+        input_counts.clear();
+        for project_node in &project.nodes {
+            if let Some(input_count) = project_node.input_count {
+                input_counts.insert(project_node.node.node_id, input_count);
+            }
+        }
+    }
+    world.insert_resource(project.export_formats());
+    world.insert_resource(project.thumbnail_exposures());
+    world.insert_resource(project.normal_maps());
+    world.insert_resource(project.group_contents());
+
+    for project_node in &project.nodes {
+        let node = {
+            let live_graph = world.get_resource::<Arc<RwLock<LiveGraph>>>().unwrap();
+            let mut live_graph = live_graph.write().unwrap();
+            match project_node.node(&mut live_graph) {
+                Ok(node) => node,
+                Err(e) => {
+                    error!("Unable to load a group: {}", e);
+                    project_node.node.clone()
+                }
+            }
+        };
+
+        spawn_gui_node_2(world, node, project_node.translation.into());
+    }
+    for edge in &project.edges {
+        AddEdgeOnly(*edge).forward(world, undo_command_manager);
+    }
+
+    // The slots of the new nodes don't have a valid `GlobalTransform` yet, so the edges have
+    // to be placed based on the local transforms.
+    for project_node in &project.nodes {
+        update_node_gui_edges(world, project_node.node.node_id);
+    }
+}
+
+/// Gathers every node and edge in the workspace into a `Project`.
+pub(crate) fn workspace_project(world: &mut World) -> Result<Project> {
+    let nodes = world
+        .query::<(&NodeIdComponent, &Transform)>()
+        .iter(world)
+        .map(|(node_id, transform)| (node_id.0, transform.translation.truncate()))
+        .collect::<Vec<(NodeId, Vec2)>>();
+    let edges = world
+        .query::<&GuiEdge>()
+        .iter(world)
+        .map(|gui_edge| Edge::from(*gui_edge))
+        .collect();
+
+    let live_graph = world
+        .get_resource::<Arc<RwLock<LiveGraph>>>()
+        .ok_or_else(|| anyhow!("there is no graph"))?;
+    let live_graph = live_graph.read().map_err(|e| anyhow!("{}", e))?;
+    Project::new(
+        &live_graph,
+        world.get_resource::<InputCounts>().unwrap(),
+        world.get_resource::<ExportFormats>().unwrap(),
+        world.get_resource::<EmbeddedImages>().unwrap(),
+        world.get_resource::<ThumbnailExposures>().unwrap(),
+        world.get_resource::<NormalMaps>().unwrap(),
+        world.get_resource::<GroupContents>().unwrap(),
+        nodes.into_iter(),
+        edges,
+    )
+}
+
+/// Removes all nodes and edges from both the workspace and the `LiveGraph`.
+fn clear_workspace(world: &mut World) {
+    let gui_edges = world
+        .query::<(Entity, &GuiEdge)>()
+        .iter(world)
+        .map(|(entity, gui_edge)| (entity, *gui_edge))
+        .collect::<Vec<(Entity, GuiEdge)>>();
+    if let Some(live_graph) = world.get_resource::<Arc<RwLock<LiveGraph>>>() {
+        let mut live_graph = live_graph.write().unwrap();
+        for (_, gui_edge) in gui_edges.iter() {
+            let _ = live_graph.remove_edge(Edge::from(*gui_edge));
+        }
+    }
+    for (entity, _) in gui_edges {
+        despawn_with_children_recursive(world, entity);
+    }
+
+    let node_ids = world
+        .query::<&NodeIdComponent>()
+        .iter(world)
+        .map(|node_id| node_id.0)
+        .collect::<Vec<NodeId>>();
+    for node_id in node_ids {
+        remove_gui_node(world, node_id);
+    }
+}
+
+pub(crate) struct ProjectPlugin;
+
+impl Plugin for ProjectPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ProjectPath::default())
+            .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Apply)
+                    .after(CustomStage::Update)
+                    .with_system(
+                        save.exclusive_system()
+                            .with_run_criteria(State::on_enter(ToolState::Save(false))),
+                    )
+                    .with_system(
+                        save.exclusive_system()
+                            .with_run_criteria(State::on_enter(ToolState::Save(true))),
+                    )
+                    .with_system(
+                        open.system()
+                            .with_run_criteria(State::on_enter(ToolState::Open))
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            );
+    }
+}
+
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Save));
+    tool_list.insert(keymap.help(Action::SaveAs));
+    tool_list.insert(keymap.help(Action::Open));
+}
+
+fn save_dialog(scan_code_input: &mut ScanCodeInput) -> Option<PathBuf> {
+    scan_code_input.reset_all();
+
+    match FileDialog::new()
+        .add_filter("Vismut project", &[PROJECT_EXTENSION])
+        .show_save_single_file()
+    {
+        Ok(Some(mut path)) => {
+            if path.extension().is_none() {
+                path.set_extension(PROJECT_EXTENSION);
+            }
+            Some(path)
+        }
+        Ok(None) => None,
+        Err(e) => {
+            warn!("Unable to get project path: {:?}\n", e);
+            None
+        }
+    }
+}
+
+fn open_dialog(scan_code_input: &mut ScanCodeInput) -> Option<PathBuf> {
+    scan_code_input.reset_all();
+
+    match FileDialog::new()
+        .add_filter("Vismut project", &[PROJECT_EXTENSION])
+        .show_open_single_file()
+    {
+        Ok(path) => path,
+        Err(e) => {
+            warn!("Unable to get project path: {:?}\n", e);
+            None
+        }
+    }
+}
+
+fn set_window_title(windows: &mut Windows, path: &Path) {
+    if let (Some(window), Some(file_name)) = (windows.get_primary_mut(), path.file_name()) {
+        window.set_title(format!("Vismut - {}", file_name.to_string_lossy()));
+    }
+}
+
+/// Saves the project to `path`, including the groups above the one that's entered.
+fn do_save(world: &mut World, path: &Path) -> Result<()> {
+    let project = workspace_project(world)?;
+    // Only the entered group is in the workspace, the rest of the project is in the levels above.
+    let mut project = world
+        .get_resource::<GroupStack>()
+        .unwrap()
+        .root_project(project);
+    let export_template = world.get_resource::<ExportTemplate>().unwrap();
+    if export_template.0 != DEFAULT_TEMPLATE {
+        project.export_template = Some(export_template.0.clone());
+    }
+    project.packing_preset = world.get_resource::<SelectedPreset>().unwrap().0.clone();
+    project.write(path)
+}
+
+/// Saves the project to where it was opened or last saved. `ToolState::Save(true)` asks where to
+/// save it, and so does saving a project that hasn't been saved yet.
+///
+/// This has access to the whole `World`, since the project is gathered with `workspace_project`.
+fn save(world: &mut World) {
+    let save_as = matches!(
+        world.get_resource::<State<ToolState>>().unwrap().current(),
+        ToolState::Save(true)
+    );
+    let project_path = world.get_resource::<ProjectPath>().unwrap().0.clone();
+    let path = match project_path {
+        Some(path) if !save_as => Some(path),
+        _ => save_dialog(&mut *world.get_resource_mut::<ScanCodeInput>().unwrap()),
+    };
+
+    if let Some(path) = path {
+        match do_save(world, &path) {
+            Ok(_) => {
+                info!("Project saved to {:?}", path);
+                set_window_title(&mut *world.get_resource_mut::<Windows>().unwrap(), &path);
+                world.get_resource_mut::<ProjectPath>().unwrap().0 = Some(path);
+            }
+            Err(e) => world
+                .get_resource_mut::<Notifications>()
+                .unwrap()
+                .error(format!("Unable to save the project: {}", e)),
+        }
+    } else {
+        info!("cancelled file dialog");
+    }
+
+    world
+        .get_resource_mut::<State<ToolState>>()
+        .unwrap()
+        .overwrite_replace(ToolState::None)
+        .unwrap();
+}
+
+fn open(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut sc_input: ResMut<ScanCodeInput>,
+    mut project_path: ResMut<ProjectPath>,
+    mut windows: ResMut<Windows>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    mut notifications: ResMut<Notifications>,
+) {
+    if let Some(path) = open_dialog(&mut *sc_input) {
+        match Project::read(&path) {
+            Ok(project) => {
+                info!("Opened project {:?}", path);
+                undo_command_manager.push(Box::new(LoadProject(project)));
+                set_window_title(&mut *windows, &path);
+                project_path.0 = Some(path);
+            }
+            Err(e) => notifications.error(format!("Unable to open the project: {}", e)),
+        }
+    } else {
+        info!("cancelled file dialog");
+    }
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
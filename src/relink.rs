@@ -0,0 +1,188 @@
+/// Finding the files of `Image` nodes whose files are missing, by searching a folder for files
+/// with the same names.
+use std::{
+    collections::HashMap,
+    ffi::OsString,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, RwLock},
+};
+
+use bevy::prelude::*;
+use native_dialog::FileDialog;
+use vismut_core::{live_graph::LiveGraph, node::node_type::NodeType, node_graph::NodeId};
+
+use crate::{
+    instruction::*,
+    keymap::{Action, Keymap},
+    notification::Notifications,
+    scan_code_input::{ScanCode, ScanCodeInput},
+    shared::NodeIdComponent,
+    undo::{gui::GuiUndoCommand, prelude::*},
+    AmbiguitySet, CustomStage, ToolState,
+};
+
+/// The `Image` nodes whose files are missing, found when the tool is started.
+#[derive(Debug, Default)]
+struct MissingImages(Vec<(NodeId, PathBuf)>);
+
+pub(crate) struct RelinkToolPlugin;
+
+impl Plugin for RelinkToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MissingImages::default())
+            .add_startup_system(setup.system().in_ambiguity_set(AmbiguitySet))
+            .add_system_set_to_stage(
+                CoreStage::Update,
+                SystemSet::new()
+                    .label(CustomStage::Update)
+                    .after(CustomStage::Setup)
+                    .with_system(
+                        relink_enter
+                            .system()
+                            .with_run_criteria(State::on_enter(ToolState::Relink))
+                            .in_ambiguity_set(AmbiguitySet),
+                    )
+                    .with_system(
+                        relink_update
+                            .system()
+                            .with_run_criteria(State::on_update(ToolState::Relink))
+                            .in_ambiguity_set(AmbiguitySet),
+                    ),
+            );
+    }
+}
+
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Relink));
+}
+
+fn relink_enter(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut instructions: ResMut<Instructions>,
+    mut missing_images: ResMut<MissingImages>,
+    live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    q_node: Query<&NodeIdComponent>,
+) {
+    let live_graph = live_graph.read().unwrap();
+    missing_images.0 = q_node
+        .iter()
+        .filter_map(|node_id| match live_graph.node(node_id.0) {
+            Ok(node) => match node.node_type {
+                NodeType::Image(path) if !path.exists() => Some((node_id.0, path)),
+                _ => None,
+            },
+            Err(_) => None,
+        })
+        .collect();
+
+    if missing_images.0.is_empty() {
+        info!("No image files are missing");
+        tool_state.overwrite_replace(ToolState::None).unwrap();
+        return;
+    }
+
+    let mut output = "## Missing files\n".to_string();
+    for (_, path) in &missing_images.0 {
+        output = format!("{}{}\n", output, path.display());
+    }
+    output = format!("{}\nEnter: Pick a folder to search", output);
+
+    instructions.insert(InstructId::Tool, output);
+}
+
+fn relink_update(
+    mut tool_state: ResMut<State<ToolState>>,
+    mut scan_code_input: ResMut<ScanCodeInput>,
+    missing_images: Res<MissingImages>,
+    mut undo_command_manager: ResMut<UndoCommandManager>,
+    mut notifications: ResMut<Notifications>,
+) {
+    if !scan_code_input.just_pressed(ScanCode::Enter)
+        && !scan_code_input.just_pressed(ScanCode::NumpadEnter)
+    {
+        return;
+    }
+    scan_code_input.reset_all();
+
+    let directory = match FileDialog::new().show_open_single_dir() {
+        Ok(Some(directory)) => directory,
+        Ok(None) => {
+            info!("cancelled file dialog");
+            tool_state.overwrite_replace(ToolState::None).unwrap();
+            return;
+        }
+        Err(e) => {
+            notifications.error(format!("Unable to get the folder to search: {:?}", e));
+            tool_state.overwrite_replace(ToolState::None).unwrap();
+            return;
+        }
+    };
+
+    let mut found = HashMap::new();
+    for (_, path) in &missing_images.0 {
+        if let Some(file_name) = path.file_name() {
+            found.insert(file_name.to_os_string(), None);
+        }
+    }
+    search(&directory, &mut found, &mut notifications);
+
+    let mut relinked = 0;
+    for (node_id, path) in &missing_images.0 {
+        if let Some(Some(new_path)) = path.file_name().and_then(|file_name| found.get(file_name)) {
+            undo_command_manager.push(Box::new(GuiUndoCommand::new(
+                *node_id,
+                NodeType::Image(path.clone()),
+                NodeType::Image(new_path.clone()),
+            )));
+            relinked += 1;
+        }
+    }
+
+    if relinked > 0 {
+        undo_command_manager.push(Box::new(Checkpoint));
+    }
+    info!(
+        "Relinked {} of {} missing files",
+        relinked,
+        missing_images.0.len()
+    );
+
+    tool_state.overwrite_replace(ToolState::None).unwrap();
+}
+
+/// Searches `directory` and its subfolders for the file names in `found`, and sets their paths.
+/// The first file that is found with a name is used, and the search stops once all are found.
+///
+/// Symbolic links to folders are not followed, since a link to a folder above it would make the
+/// search go on forever.
+fn search(
+    directory: &Path,
+    found: &mut HashMap<OsString, Option<PathBuf>>,
+    notifications: &mut Notifications,
+) {
+    let entries = match fs::read_dir(directory) {
+        Ok(entries) => entries,
+        Err(e) => {
+            notifications.error(format!("Unable to search {:?}: {}", directory, e));
+            return;
+        }
+    };
+
+    for entry in entries.flatten() {
+        if found.values().all(Option::is_some) {
+            return;
+        }
+
+        let is_dir = match entry.file_type() {
+            Ok(file_type) => file_type.is_dir(),
+            Err(_) => continue,
+        };
+
+        if is_dir {
+            search(&entry.path(), found, notifications);
+        } else if let Some(slot @ None) = found.get_mut(&entry.file_name()) {
+            *slot = Some(entry.path());
+        }
+    }
+}
@@ -0,0 +1,123 @@
+/// Renders the outputs of a project from the command line, without opening a window.
+///
+/// Usage: `vismut render <project> --out <directory>`
+use std::{
+    fs,
+    path::PathBuf,
+    sync::{Arc, RwLock},
+};
+
+use anyhow::{anyhow, bail, Result};
+use vismut_core::{live_graph::LiveGraph, texture_processor::TextureProcessor};
+
+use crate::{
+    export_outputs::{export_outputs, ExportSettings},
+    packing::{PackingPresets, SelectedPreset},
+    project::{Project, ProjectPath},
+};
+
+pub(crate) const SUBCOMMAND: &str = "render";
+const USAGE: &str = "Usage: vismut render <project> --out <directory>";
+
+#[derive(Debug)]
+struct RenderArgs {
+    project: PathBuf,
+    out: PathBuf,
+}
+
+impl RenderArgs {
+    fn parse(args: &[String]) -> Result<Self> {
+        let mut project = None;
+        let mut out = None;
+        let mut args = args.iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--out" | "-o" => {
+                    out = Some(PathBuf::from(
+                        args.next()
+                            .ok_or_else(|| anyhow!("missing directory after {}", arg))?,
+                    ));
+                }
+                _ if project.is_none() && !arg.starts_with('-') => {
+                    project = Some(PathBuf::from(arg));
+                }
+                _ => bail!("unexpected argument: {}", arg),
+            }
+        }
+
+        Ok(Self {
+            project: project.ok_or_else(|| anyhow!("missing project path"))?,
+            out: out.ok_or_else(|| anyhow!("missing --out directory"))?,
+        })
+    }
+}
+
+/// Runs the render subcommand with the arguments that follow it, and returns the process exit
+/// code.
+pub(crate) fn run(args: &[String]) -> i32 {
+    let args = match RenderArgs::parse(args) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("error: {}\n{}", e, USAGE);
+            return 2;
+        }
+    };
+
+    match render(&args) {
+        Ok(0) => 0,
+        Ok(failed) => {
+            eprintln!("error: {} output(s) failed to export", failed);
+            1
+        }
+        Err(e) => {
+            eprintln!("error: {}", e);
+            1
+        }
+    }
+}
+
+/// Processes all outputs of the project and writes them to disk. Returns the number of outputs
+/// that failed.
+fn render(args: &RenderArgs) -> Result<usize> {
+    let project = Project::read(&args.project)?;
+    let tex_pro = TextureProcessor::new(Arc::new(1_000_000_000.into()));
+    let live_graph: Arc<RwLock<LiveGraph>> = project.live_graph(&tex_pro)?;
+    let export_formats = project.export_formats();
+    let export_template = project.export_template();
+    let project_name = ProjectPath(Some(args.project.clone())).name();
+    let packing_presets = PackingPresets::load();
+    let preset = packing_presets.selected(&SelectedPreset(project.packing_preset.clone()))?;
+    let normal_maps = project.normal_maps();
+    let settings = ExportSettings {
+        formats: &export_formats,
+        template: &export_template,
+        project: &project_name,
+        preset,
+        normal_maps: &normal_maps,
+        edges: &project.edges,
+    };
+
+    let output_ids = live_graph
+        .read()
+        .map_err(|e| anyhow!("{}", e))?
+        .output_ids();
+    if output_ids.is_empty() {
+        bail!("the project does not have any output nodes");
+    }
+
+    fs::create_dir_all(&args.out)?;
+
+    let mut failed = 0;
+    for (exported, result) in export_outputs(&live_graph, &settings, &args.out)? {
+        match result {
+            Ok(path) => println!("{}", path.display()),
+            Err(e) => {
+                eprintln!("error: {}: {}", exported, e);
+                failed += 1;
+            }
+        }
+    }
+
+    Ok(failed)
+}
@@ -6,9 +6,10 @@ use bevy::{
     utils::HashSet,
 };
 use num_enum::TryFromPrimitive;
+use serde::{Deserialize, Serialize};
 use std::convert::TryFrom;
 
-#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq, TryFromPrimitive)]
+#[derive(Clone, Copy, Debug, Deserialize, Eq, Hash, PartialEq, Serialize, TryFromPrimitive)]
 #[repr(u32)]
 pub enum ScanCode {
     Error = 0,
@@ -4,16 +4,19 @@ use std::{
 };
 
 use crate::{
+    node_error::{NodeError, ProcessingFailed},
+    node_registry,
+    process_tool::ProcessingMode,
     shared::{NodeIdComponent, NodeStateComponent, SlotTypeComponent},
     thumbnail::{Thumbnail, ThumbnailState, THUMBNAIL_SIZE},
     AmbiguitySet, CustomStage, Draggable, Hoverable, Hovered,
 };
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use rand::Rng;
 use vismut_core::{
     edge::Edge as CoreEdge,
     live_graph::{LiveGraph, NodeState},
-    node::{node_type::NodeType, Node, Side},
+    node::{node_type::NodeType, Node, Side, SlotType},
     node_graph::{NodeId, SlotId},
     texture_processor::TextureProcessor,
 };
@@ -25,24 +28,6 @@ pub const NODE_SIZE: f32 = THUMBNAIL_SIZE + SLOT_SIZE * 2. + SLOT_MARGIN * 2.;
 const SLOT_DISTANCE_Y: f32 = 32. + SLOT_MARGIN;
 const SMALLEST_DEPTH_UNIT: f32 = f32::EPSILON * 500.;
 
-trait Name {
-    fn title(&self) -> String;
-}
-
-impl Name for NodeType {
-    fn title(&self) -> String {
-        match self {
-            Self::CombineRgba => "Combine",
-            Self::Image(_) => "Image",
-            Self::OutputRgba(_) => "Output",
-            Self::SeparateRgba => "Separate",
-            Self::Value(_) => "Value",
-            _ => "Unnamed",
-        }
-        .into()
-    }
-}
-
 // I'm saving the start and end variables for when I want to select the edges themselves.
 #[derive(Component, Copy, Clone, Debug)]
 pub(crate) struct Edge {
@@ -92,11 +77,38 @@ pub(crate) struct SlotBundle {
     slot_type: SlotTypeComponent,
 }
 
+/// The number of input slots on nodes that can take any number of inputs, like `Mix` nodes. Nodes
+/// that are not in here have the inputs given by `Node::input_slots()`.
+///
+/// This is kept outside of the GUI nodes so a node gets its inputs back when its removal is
+/// undone.
+#[derive(Debug, Default)]
+pub(crate) struct InputCounts(pub HashMap<NodeId, usize>);
+
+impl std::ops::Deref for InputCounts {
+    type Target = HashMap<NodeId, usize>;
+    fn deref(&self) -> &HashMap<NodeId, usize> {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for InputCounts {
+    fn deref_mut(&mut self) -> &mut HashMap<NodeId, usize> {
+        &mut self.0
+    }
+}
+
+/// Returns true if the node can have any number of inputs.
+pub(crate) fn has_variable_inputs(node_type: &NodeType) -> bool {
+    matches!(node_type, NodeType::Mix(_))
+}
+
 pub(crate) struct SyncGraphPlugin;
 
 impl Plugin for SyncGraphPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup.system())
+        app.insert_resource(InputCounts::default())
+            .add_startup_system(setup.system())
             .add_system_set_to_stage(
                 CoreStage::Update,
                 SystemSet::new()
@@ -108,9 +120,13 @@ impl Plugin for SyncGraphPlugin {
     }
 }
 
-fn setup(mut commands: Commands, tex_pro: Res<Arc<TextureProcessor>>) {
+fn setup(
+    mut commands: Commands,
+    tex_pro: Res<Arc<TextureProcessor>>,
+    processing_mode: Res<ProcessingMode>,
+) {
     let mut live_graph = LiveGraph::new(Arc::clone(&tex_pro.add_buffer_queue));
-    live_graph.auto_update = true;
+    live_graph.auto_update = *processing_mode == ProcessingMode::Auto;
     live_graph.use_cache = true;
     let live_graph = Arc::new(RwLock::new(live_graph));
 
@@ -129,7 +145,9 @@ fn sync_graph(
         &NodeIdComponent,
         &mut NodeStateComponent,
         &mut ThumbnailState,
+        Option<&NodeError>,
     )>,
+    q_edge: Query<&Edge>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
 ) {
     let changed_node_ids = live_graph.write().unwrap().changed_consume();
@@ -137,9 +155,9 @@ fn sync_graph(
     for node_id in changed_node_ids {
         info!("{:?} changed {{", node_id);
 
-        if let Some((node_gui_e, _, mut node_state, mut thumbnail_state)) = q_node
+        if let Some((node_gui_e, _, mut node_state, mut thumbnail_state, node_error)) = q_node
             .iter_mut()
-            .find(|(_, node_id_query, _, _)| node_id_query.0 == node_id)
+            .find(|(_, node_id_query, _, _, _)| node_id_query.0 == node_id)
         {
             if live_graph.read().unwrap().has_node(node_id).is_err() {
                 info!("Removing the node");
@@ -159,6 +177,25 @@ fn sync_graph(
                 if node_state_actual == NodeState::Clean {
                     *thumbnail_state = ThumbnailState::Missing;
                 }
+
+                if processing_failed(
+                    &live_graph.read().unwrap(),
+                    &q_edge,
+                    node_id,
+                    node_state.0,
+                    node_state_actual,
+                ) {
+                    info!("Processing failed");
+                    commands.entity(node_gui_e).insert(ProcessingFailed);
+                    if node_error.is_none() {
+                        commands
+                            .entity(node_gui_e)
+                            .insert(NodeError("Unable to process the node".to_string()));
+                    }
+                } else {
+                    commands.entity(node_gui_e).remove::<ProcessingFailed>();
+                }
+
                 node_state.0 = node_state_actual;
             } else {
                 error!(
@@ -172,6 +209,31 @@ fn sync_graph(
     }
 }
 
+/// The core doesn't report errors, so a failure is recognized by a node going back to `Dirty`
+/// while it's being processed, even though all its inputs are `Clean`. A node that was `Dirty`
+/// before is counted too, since it may have been requested and processed between two frames.
+/// This only happens while the graph updates itself, since nothing is processed otherwise.
+fn processing_failed(
+    live_graph: &LiveGraph,
+    q_edge: &Query<&Edge>,
+    node_id: NodeId,
+    node_state_previous: NodeState,
+    node_state_actual: NodeState,
+) -> bool {
+    live_graph.auto_update
+        && node_state_actual == NodeState::Dirty
+        && node_state_previous != NodeState::Clean
+        && q_edge
+            .iter()
+            .filter(|edge| edge.input_slot.node_id == node_id)
+            .all(|edge| {
+                matches!(
+                    live_graph.node_state(edge.output_slot.node_id),
+                    Ok(NodeState::Clean)
+                )
+            })
+}
+
 pub(crate) fn stretch_between(
     sprite: &mut Sprite,
     transform: &mut Transform,
@@ -187,6 +249,121 @@ pub(crate) fn stretch_between(
     sprite.custom_size = Some(Vec2::new(distance, 5.));
 }
 
+/// The input slots of a node as they are shown in the GUI. Nodes with variable inputs get extra
+/// slots of the same type as their last slot, up to `input_count`.
+pub(crate) fn input_slots(node: &Node, input_count: Option<usize>) -> Vec<(SlotId, SlotType)> {
+    let mut slots: Vec<(SlotId, SlotType)> = node
+        .input_slots()
+        .into_iter()
+        .map(|slot| (slot.slot_id, slot.slot_type))
+        .collect();
+
+    if let (Some(input_count), Some(&(_, slot_type))) = (input_count, slots.last()) {
+        if has_variable_inputs(&node.node_type) {
+            for i in slots.len()..input_count {
+                slots.push((SlotId(i as u32), slot_type));
+            }
+        }
+    }
+
+    slots
+}
+
+fn slot_bundle(
+    node_id: NodeId,
+    side: Side,
+    slot_id: SlotId,
+    slot_type: SlotType,
+    index: usize,
+) -> SlotBundle {
+    let x = match side {
+        Side::Input => -SLOT_DISTANCE_X,
+        Side::Output => SLOT_DISTANCE_X,
+    };
+
+    SlotBundle {
+        sprite_bundle: SpriteBundle {
+            sprite: Sprite {
+                color: Color::rgb(0.5, 0.5, 0.5),
+                custom_size: Some(Vec2::new(SLOT_SIZE, SLOT_SIZE)),
+                ..Default::default()
+            },
+            transform: Transform::from_translation(Vec3::new(
+                x,
+                THUMBNAIL_SIZE / 2. - SLOT_SIZE / 2. - SLOT_DISTANCE_Y * index as f32,
+                SMALLEST_DEPTH_UNIT,
+            )),
+            ..Default::default()
+        },
+        slot: Slot {
+            node_id,
+            side,
+            slot_id,
+        },
+        slot_type: SlotTypeComponent(slot_type),
+        ..Default::default()
+    }
+}
+
+/// Sets the number of input slots on a node with variable inputs, and adds or removes GUI slots
+/// to match. Any edges connected to removed slots need to be removed before calling this.
+pub fn set_gui_input_count(world: &mut World, node_id: NodeId, input_count: usize) {
+    let node = match world
+        .get_resource::<Arc<RwLock<LiveGraph>>>()
+        .unwrap()
+        .read()
+        .unwrap()
+        .node(node_id)
+    {
+        Ok(node) => node,
+        Err(e) => {
+            error!("could not find the node to change the inputs of: {}", e);
+            return;
+        }
+    };
+
+    world
+        .get_resource_mut::<InputCounts>()
+        .unwrap()
+        .insert(node_id, input_count);
+
+    let node_e = if let Some((entity, _)) = world
+        .query::<(Entity, &NodeIdComponent)>()
+        .iter(world)
+        .find(|(_, node_id_cmp)| node_id_cmp.0 == node_id)
+    {
+        entity
+    } else {
+        return;
+    };
+
+    let slots = input_slots(&node, Some(input_count));
+
+    let mut existing_slot_ids = Vec::new();
+    let mut slots_to_remove = Vec::new();
+    for (entity, slot) in world.query::<(Entity, &Slot)>().iter(world) {
+        if slot.node_id == node_id && slot.side == Side::Input {
+            if slots.iter().any(|(slot_id, _)| *slot_id == slot.slot_id) {
+                existing_slot_ids.push(slot.slot_id);
+            } else {
+                slots_to_remove.push(entity);
+            }
+        }
+    }
+
+    for entity in slots_to_remove {
+        despawn_with_children_recursive(world, entity);
+    }
+
+    world.entity_mut(node_e).with_children(|parent| {
+        for (i, (slot_id, slot_type)) in slots.into_iter().enumerate() {
+            if !existing_slot_ids.contains(&slot_id) {
+                parent.spawn_bundle(slot_bundle(node_id, Side::Input, slot_id, slot_type, i));
+            }
+        }
+    });
+}
+
 pub fn remove_gui_node(world: &mut World, node_id: NodeId) {
     world
         .get_resource::<Arc<RwLock<LiveGraph>>>()
@@ -216,8 +393,11 @@ pub fn spawn_gui_node_2(world: &mut World, node: Node, translation: Vec2) -> Ent
         .get_resource::<AssetServer>()
         .unwrap()
         .load("fonts/FiraSans-Regular.ttf");
+    let input_count = world
+        .get_resource::<InputCounts>()
+        .and_then(|input_counts| input_counts.get(&node.node_id).copied());
 
-    let title = node.node_type.title();
+    let title = node_registry::info(&node.node_type).title;
     let font_size = SLOT_SIZE;
     let text_y_pos = NODE_SIZE / 2.0 - font_size / 2.0;
     let text_style = TextStyle {
@@ -267,54 +447,25 @@ pub fn spawn_gui_node_2(world: &mut World, node: Node, translation: Vec2) -> Ent
                 })
                 .insert(Thumbnail);
 
-            for (i, slot) in node.input_slots().into_iter().enumerate() {
-                parent.spawn_bundle(SlotBundle {
-                    sprite_bundle: SpriteBundle {
-                        sprite: Sprite {
-                            color: Color::rgb(0.5, 0.5, 0.5),
-                            custom_size: Some(Vec2::new(SLOT_SIZE, SLOT_SIZE)),
-                            ..Default::default()
-                        },
-                        transform: Transform::from_translation(Vec3::new(
-                            -SLOT_DISTANCE_X,
-                            THUMBNAIL_SIZE / 2. - SLOT_SIZE / 2. - SLOT_DISTANCE_Y * i as f32,
-                            SMALLEST_DEPTH_UNIT,
-                        )),
-                        ..Default::default()
-                    },
-                    slot: Slot {
-                        node_id: node.node_id,
-                        side: Side::Input,
-                        slot_id: slot.slot_id,
-                    },
-                    slot_type: SlotTypeComponent(slot.slot_type),
-                    ..Default::default()
-                });
+            for (i, (slot_id, slot_type)) in input_slots(&node, input_count).into_iter().enumerate()
+            {
+                parent.spawn_bundle(slot_bundle(
+                    node.node_id,
+                    Side::Input,
+                    slot_id,
+                    slot_type,
+                    i,
+                ));
             }
 
             for (i, slot) in node.output_slots().into_iter().enumerate() {
-                parent.spawn_bundle(SlotBundle {
-                    sprite_bundle: SpriteBundle {
-                        sprite: Sprite {
-                            color: Color::rgb(0.5, 0.5, 0.5),
-                            custom_size: Some(Vec2::new(SLOT_SIZE, SLOT_SIZE)),
-                            ..Default::default()
-                        },
-                        transform: Transform::from_translation(Vec3::new(
-                            SLOT_DISTANCE_X,
-                            THUMBNAIL_SIZE / 2. - SLOT_SIZE / 2. - SLOT_DISTANCE_Y * i as f32,
-                            SMALLEST_DEPTH_UNIT,
-                        )),
-                        ..Default::default()
-                    },
-                    slot: Slot {
-                        node_id: node.node_id,
-                        side: Side::Output,
-                        slot_id: slot.slot_id,
-                    },
-                    slot_type: SlotTypeComponent(slot.slot_type),
-                    ..Default::default()
-                });
+                parent.spawn_bundle(slot_bundle(
+                    node.node_id,
+                    Side::Output,
+                    slot.slot_id,
+                    slot.slot_type,
+                    i,
+                ));
             }
         })
         .id();
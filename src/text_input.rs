@@ -0,0 +1,296 @@
+/// A single line text field, used wherever a value is typed in. It has a caret that can be moved
+/// with the arrow keys, `Home` and `End`, a selection made by holding `Shift` or pressing
+/// `Ctrl A`, and pasting with `Ctrl V`. `Enter` submits the text if it's valid for the field, and
+/// `Esc` cancels.
+///
+/// The field that is being typed in is kept in the `TextField` resource, so other systems know to
+/// leave the keyboard alone.
+use arboard::Clipboard;
+use bevy::prelude::*;
+use vismut_core::slot_data::Size as CoreSize;
+
+use crate::{
+    hotkeys::{control_pressed, shift_pressed},
+    scan_code_input::{ScanCode, ScanCodeInput},
+};
+
+const CARET: char = '|';
+
+/// What a field accepts.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub(crate) enum Validation {
+    Text,
+    /// A whole number that is 0 or more.
+    Integer,
+    Float,
+    /// A width and height, like `256x256`.
+    Size,
+}
+
+impl Validation {
+    fn accepts(self, c: char) -> bool {
+        match self {
+            Self::Text => !c.is_control(),
+            Self::Integer => c.is_ascii_digit(),
+            Self::Float => c.is_ascii_digit() || matches!(c, '.' | '-' | 'e' | 'E'),
+            Self::Size => c.is_ascii_digit() || c == 'x',
+        }
+    }
+
+    fn check(self, text: &str) -> Result<(), &'static str> {
+        let valid = match self {
+            Self::Text => true,
+            Self::Integer => text.parse::<usize>().is_ok(),
+            Self::Float => text.parse::<f32>().is_ok(),
+            Self::Size => parse_size(text).is_some(),
+        };
+
+        if valid {
+            Ok(())
+        } else {
+            Err(match self {
+                Self::Text => unreachable!(),
+                Self::Integer => "Should be a whole number, like 2",
+                Self::Float => "Should be a number, like -1.5",
+                Self::Size => "Should be a width and height, like 256x256",
+            })
+        }
+    }
+}
+
+/// Parses a size written like `256x256`.
+pub(crate) fn parse_size(text: &str) -> Option<CoreSize> {
+    let (width, height) = text.split_once('x')?;
+    Some(CoreSize::new(width.parse().ok()?, height.parse().ok()?))
+}
+
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub(crate) enum TextInputEvent {
+    /// Enter was pressed and the text is valid.
+    Submit(String),
+    Cancel,
+}
+
+#[derive(Clone, Debug)]
+pub(crate) struct TextInput {
+    text: Vec<char>,
+    /// The position of the caret, in characters.
+    caret: usize,
+    /// The other end of the selection, if anything is selected.
+    anchor: Option<usize>,
+    validation: Validation,
+    error: Option<String>,
+    started: bool,
+}
+
+impl TextInput {
+    /// Creates a field with `text` in it, all selected so typing replaces it.
+    pub fn new(text: impl Into<String>, validation: Validation) -> Self {
+        let text: Vec<char> = text.into().chars().collect();
+        Self {
+            caret: text.len(),
+            anchor: if text.is_empty() { None } else { Some(0) },
+            text,
+            validation,
+            error: None,
+            started: false,
+        }
+    }
+
+    pub fn text(&self) -> String {
+        self.text.iter().collect()
+    }
+
+    /// Shows an error under the field, for problems that are only found when the value is used.
+    pub fn set_error(&mut self, error: impl Into<String>) {
+        self.error = Some(error.into());
+    }
+
+    /// The start and end of the selection.
+    fn selection(&self) -> Option<(usize, usize)> {
+        self.anchor
+            .filter(|anchor| *anchor != self.caret)
+            .map(|anchor| (anchor.min(self.caret), anchor.max(self.caret)))
+    }
+
+    /// Removes the selected text, returns false if nothing was selected.
+    fn delete_selection(&mut self) -> bool {
+        let selection = self.selection();
+        self.anchor = None;
+
+        if let Some((start, end)) = selection {
+            self.text.drain(start..end);
+            self.caret = start;
+            true
+        } else {
+            false
+        }
+    }
+
+    fn insert(&mut self, text: &str) {
+        self.delete_selection();
+        for c in text.chars().filter(|c| self.validation.accepts(*c)) {
+            self.text.insert(self.caret, c);
+            self.caret += 1;
+        }
+    }
+
+    fn move_caret(&mut self, to: usize, select: bool) {
+        if select {
+            self.anchor.get_or_insert(self.caret);
+        } else {
+            self.anchor = None;
+        }
+        self.caret = to.min(self.text.len());
+    }
+
+    fn paste(&mut self) {
+        match Clipboard::new().and_then(|mut clipboard| clipboard.get_text()) {
+            Ok(text) => self.insert(text.lines().next().unwrap_or_default()),
+            Err(e) => warn!("Unable to paste: {}", e),
+        }
+    }
+
+    fn submit(&mut self) -> Option<TextInputEvent> {
+        let text = self.text();
+        match self.validation.check(&text) {
+            Ok(()) => Some(TextInputEvent::Submit(text)),
+            Err(error) => {
+                self.error = Some(error.to_string());
+                None
+            }
+        }
+    }
+
+    /// Applies this frame's typing, and returns what happened if the field is done.
+    pub fn update(
+        &mut self,
+        char_input_events: &mut EventReader<ReceivedCharacter>,
+        scan_code_input: &mut ScanCodeInput,
+    ) -> Option<TextInputEvent> {
+        // The characters from the key that focused the field don't belong in it.
+        if !self.started {
+            self.started = true;
+            char_input_events.iter().for_each(drop);
+            return None;
+        }
+
+        if scan_code_input.clear_just_pressed(ScanCode::Escape) {
+            return Some(TextInputEvent::Cancel);
+        }
+
+        let control = control_pressed(scan_code_input);
+        let select = shift_pressed(scan_code_input);
+
+        for c in char_input_events.iter().map(|event| event.char) {
+            match c {
+                // Backspace gives `\u{8}` on some platforms and `\u{7f}` on others, like macOS.
+                '\u{8}' | '\u{7f}' => {
+                    if !self.delete_selection() && self.caret > 0 {
+                        self.caret -= 1;
+                        self.text.remove(self.caret);
+                    }
+                }
+                '\r' => return self.submit(),
+                // Holding control gives control characters on some platforms, and letters on
+                // others, neither of which are typed.
+                _ if control || c.is_control() => continue,
+                _ => self.insert(&c.to_string()),
+            }
+            self.error = None;
+        }
+
+        if scan_code_input.clear_just_pressed(ScanCode::NumpadEnter) {
+            return self.submit();
+        }
+
+        // Delete doesn't give a character on every platform, so it's read from the key instead.
+        if scan_code_input.clear_just_pressed(ScanCode::Delete) {
+            if !self.delete_selection() && self.caret < self.text.len() {
+                self.text.remove(self.caret);
+            }
+            self.error = None;
+        }
+
+        if scan_code_input.clear_just_pressed(ScanCode::ArrowLeft) {
+            match self.selection() {
+                Some((start, _)) if !select => self.move_caret(start, false),
+                _ => self.move_caret(self.caret.saturating_sub(1), select),
+            }
+        }
+        if scan_code_input.clear_just_pressed(ScanCode::ArrowRight) {
+            match self.selection() {
+                Some((_, end)) if !select => self.move_caret(end, false),
+                _ => self.move_caret(self.caret + 1, select),
+            }
+        }
+        if scan_code_input.clear_just_pressed(ScanCode::Home) {
+            self.move_caret(0, select);
+        }
+        if scan_code_input.clear_just_pressed(ScanCode::End) {
+            self.move_caret(self.text.len(), select);
+        }
+        if control && scan_code_input.clear_just_pressed(ScanCode::KeyA) {
+            self.anchor = Some(0);
+            self.caret = self.text.len();
+        }
+        if control && scan_code_input.clear_just_pressed(ScanCode::KeyV) {
+            self.paste();
+            self.error = None;
+        }
+
+        None
+    }
+
+    /// The text with the caret, the selection in brackets, and any error on the line below.
+    pub fn display(&self) -> String {
+        let mut display = String::new();
+        let selection = self.selection();
+
+        for i in 0..=self.text.len() {
+            if selection.map_or(false, |(start, _)| start == i) {
+                display.push('[');
+            }
+            if selection.map_or(false, |(_, end)| end == i) {
+                display.push(']');
+            }
+            if i == self.caret {
+                display.push(CARET);
+            }
+            if let Some(c) = self.text.get(i) {
+                display.push(*c);
+            }
+        }
+
+        if let Some(error) = &self.error {
+            display = format!("{}\n{}", display, error);
+        }
+
+        display
+    }
+}
+
+/// The text input that has keyboard focus, if any.
+#[derive(Debug, Default)]
+pub(crate) struct TextField(pub Option<TextInput>);
+
+impl std::ops::Deref for TextField {
+    type Target = Option<TextInput>;
+    fn deref(&self) -> &Option<TextInput> {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for TextField {
+    fn deref_mut(&mut self) -> &mut Option<TextInput> {
+        &mut self.0
+    }
+}
+
+pub(crate) struct TextInputPlugin;
+
+impl Plugin for TextInputPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TextField::default());
+    }
+}
@@ -1,14 +1,23 @@
 use crate::{
+    export_format::{pixels, Pixels},
+    node_error::NodeError,
+    normal_map::{NormalMap, NormalMaps},
     shared::{LiveGraphComponent, NodeIdComponent},
+    sync_graph::Edge as GuiEdge,
+    undo::prelude::*,
     AmbiguitySet, CustomStage,
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use bevy::{
     prelude::*,
     render::render_resource::{Extent3d, TextureDimension, TextureFormat},
 };
-use std::sync::{Arc, RwLock};
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+};
 use vismut_core::{
+    edge::Edge,
     error::TexProError,
     live_graph::{LiveGraph, NodeState},
     node::{embed::EmbeddedSlotDataId, node_type::NodeType, Node, ResizeFilter, ResizePolicy},
@@ -24,6 +33,11 @@ pub(crate) const THUMBNAIL_SIZE: f32 = 128.;
 #[derive(Component)]
 pub(crate) struct Thumbnail;
 
+/// The normal map settings a thumbnail that is being processed is made with, see
+/// `NormalMaps::applied`.
+#[derive(Component, Copy, Clone, Debug)]
+struct ThumbnailNormalMap(NormalMap);
+
 pub(crate) struct ThumbnailPlugin;
 
 #[derive(Component, Copy, Clone, Debug, PartialEq)]
@@ -40,9 +54,69 @@ impl Default for ThumbnailState {
     }
 }
 
+/// The exposure of node thumbnails in stops, used to view images with values outside of the
+/// displayable range, like HDR images. Only affects the thumbnail, not the values in the graph.
+/// Nodes that are not in here have an exposure of 0.
+#[derive(Debug, Default)]
+pub(crate) struct ThumbnailExposures(pub HashMap<NodeId, f32>);
+
+impl std::ops::Deref for ThumbnailExposures {
+    type Target = HashMap<NodeId, f32>;
+    fn deref(&self) -> &HashMap<NodeId, f32> {
+        &self.0
+    }
+}
+
+impl std::ops::DerefMut for ThumbnailExposures {
+    fn deref_mut(&mut self) -> &mut HashMap<NodeId, f32> {
+        &mut self.0
+    }
+}
+
+impl ThumbnailExposures {
+    pub fn exposure(&self, node_id: NodeId) -> f32 {
+        self.get(&node_id).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct SetThumbnailExposure {
+    pub node_id: NodeId,
+    pub from: f32,
+    pub to: f32,
+}
+impl UndoCommand for SetThumbnailExposure {
+    fn forward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        set_thumbnail_exposure(world, self.node_id, self.to);
+    }
+
+    fn backward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        set_thumbnail_exposure(world, self.node_id, self.from);
+    }
+}
+
+/// Sets the exposure, and remakes the thumbnail if the node has one.
+fn set_thumbnail_exposure(world: &mut World, node_id: NodeId, exposure: f32) {
+    if let Some(mut exposures) = world.get_resource_mut::<ThumbnailExposures>() {
+        exposures.insert(node_id, exposure);
+    }
+
+    let mut q_node = world.query::<(&NodeIdComponent, &mut ThumbnailState)>();
+    if let Some((_, mut thumb_state)) = q_node
+        .iter_mut(world)
+        .find(|(node_id_iter, _)| node_id_iter.0 == node_id)
+    {
+        if *thumb_state == ThumbnailState::Present {
+            *thumb_state = ThumbnailState::Missing;
+        }
+    }
+}
+
 impl Plugin for ThumbnailPlugin {
     fn build(&self, app: &mut App) {
         app.insert_non_send_resource(Vec::<TexProThumb>::new())
+            .insert_resource(ThumbnailExposures::default())
+            .insert_resource(NormalMaps::default())
             .add_system_set_to_stage(
                 CoreStage::Update,
                 SystemSet::new()
@@ -61,27 +135,45 @@ impl Plugin for ThumbnailPlugin {
 fn thumbnail_state_changed(
     mut commands: Commands,
     mut q_node: Query<(Entity, &NodeIdComponent, &mut ThumbnailState), Changed<ThumbnailState>>,
+    q_edge: Query<&GuiEdge>,
     tex_pro: Res<Arc<TextureProcessor>>,
     live_graph: Res<Arc<RwLock<LiveGraph>>>,
+    normal_maps: Res<NormalMaps>,
 ) {
+    let edges = q_edge
+        .iter()
+        .map(|gui_edge| Edge::from(*gui_edge))
+        .collect::<Vec<Edge>>();
+
     for (entity, node_id, mut thumb_state) in q_node
         .iter_mut()
         .filter(|(_, _, state)| **state == ThumbnailState::Missing)
     {
-        if let Some(thumb_live_graph) = thumbnail_processor(
+        match thumbnail_processor(
             &tex_pro,
             &live_graph,
             node_id.0,
             Size::new(THUMBNAIL_SIZE as f32, THUMBNAIL_SIZE as f32),
         ) {
-            let thumb_live_graph = Arc::new(RwLock::new(thumb_live_graph));
-            tex_pro
-                .push_live_graph(Arc::clone(&thumb_live_graph))
-                .unwrap();
-            commands
-                .entity(entity)
-                .insert(LiveGraphComponent(thumb_live_graph));
-            *thumb_state = ThumbnailState::Processing;
+            Ok(thumb_live_graph) => {
+                let normal_map =
+                    normal_maps.applied(&live_graph.read().unwrap(), node_id.0, &edges);
+                let thumb_live_graph = Arc::new(RwLock::new(thumb_live_graph));
+                tex_pro
+                    .push_live_graph(Arc::clone(&thumb_live_graph))
+                    .unwrap();
+                commands
+                    .entity(entity)
+                    .insert(LiveGraphComponent(thumb_live_graph))
+                    .insert(ThumbnailNormalMap(normal_map));
+                *thumb_state = ThumbnailState::Processing;
+            }
+            Err(e) => {
+                info!("Failed to create thumbnail processor for {}", node_id.0);
+                commands
+                    .entity(entity)
+                    .insert(NodeError(format!("Unable to create thumbnail: {}", e)));
+            }
         }
     }
 }
@@ -95,10 +187,15 @@ fn get_thumbnail_loop(
         &NodeIdComponent,
         &mut ThumbnailState,
         &LiveGraphComponent,
+        Option<&ThumbnailNormalMap>,
     )>,
+    exposures: Res<ThumbnailExposures>,
 ) {
-    for (node_e, node_id, mut thumb_state, live_graph) in q_node.iter_mut() {
-        let image = match try_get_output(&live_graph.0) {
+    for (node_e, node_id, mut thumb_state, live_graph, normal_map) in q_node.iter_mut() {
+        let normal_map = normal_map
+            .map(|normal_map| normal_map.0)
+            .unwrap_or_default();
+        let image = match try_get_output(&live_graph.0, exposures.exposure(node_id.0), normal_map) {
             Ok(image) => {
                 let image_handle = images.add(image);
                 Some(image_handle)
@@ -118,7 +215,11 @@ fn get_thumbnail_loop(
             }
 
             *thumb_state = ThumbnailState::Present;
-            commands.entity(node_e).remove::<LiveGraphComponent>();
+            commands
+                .entity(node_e)
+                .remove::<LiveGraphComponent>()
+                .remove::<ThumbnailNormalMap>()
+                .remove::<NodeError>();
         }
     }
 }
@@ -131,44 +232,44 @@ fn thumbnail_processor(
     live_graph: &Res<Arc<RwLock<LiveGraph>>>,
     node_id: NodeId,
     size: Size,
-) -> Option<LiveGraph> {
-    if let Ok(slot_data) = live_graph.read().unwrap().slot_data(node_id, SlotId(0)) {
-        let mut live_graph_thumb = LiveGraph::new(Arc::clone(&tex_pro.add_buffer_queue));
-        let embedded_slot_data_id = live_graph_thumb
-            .embed_slot_data_with_id(Arc::clone(slot_data), EmbeddedSlotDataId(0))
-            .unwrap();
-
-        let n_embedded = live_graph_thumb
-            .add_node(Node::new(NodeType::Embed(embedded_slot_data_id)))
-            .unwrap();
-        let n_out = live_graph_thumb
-            .add_node(
-                Node::new(NodeType::OutputRgba("out".into()))
-                    .resize_policy(ResizePolicy::SpecificSize(TPSize::new(
-                        size.width as u32,
-                        size.height as u32,
-                    )))
-                    .resize_filter(ResizeFilter::Triangle),
-            )
-            .unwrap();
-
-        live_graph_thumb
-            .connect(n_embedded, n_out, SlotId(0), SlotId(0))
-            .unwrap();
-
-        live_graph_thumb.auto_update = true;
-
-        info!("Created thumbnail processor for {}", node_id);
-
-        Some(live_graph_thumb)
-    } else {
-        info!("Failed to create thumbnail processor for {}", node_id);
-        None
-    }
+) -> Result<LiveGraph> {
+    let slot_data = Arc::clone(
+        live_graph
+            .read()
+            .map_err(|e| anyhow!("{}", e))?
+            .slot_data(node_id, SlotId(0))?,
+    );
+
+    let mut live_graph_thumb = LiveGraph::new(Arc::clone(&tex_pro.add_buffer_queue));
+    let embedded_slot_data_id =
+        live_graph_thumb.embed_slot_data_with_id(slot_data, EmbeddedSlotDataId(0))?;
+
+    let n_embedded =
+        live_graph_thumb.add_node(Node::new(NodeType::Embed(embedded_slot_data_id)))?;
+    let n_out = live_graph_thumb.add_node(
+        Node::new(NodeType::OutputRgba("out".into()))
+            .resize_policy(ResizePolicy::SpecificSize(TPSize::new(
+                size.width as u32,
+                size.height as u32,
+            )))
+            .resize_filter(ResizeFilter::Triangle),
+    )?;
+
+    live_graph_thumb.connect(n_embedded, n_out, SlotId(0), SlotId(0))?;
+
+    live_graph_thumb.auto_update = true;
+
+    info!("Created thumbnail processor for {}", node_id);
+
+    Ok(live_graph_thumb)
 }
 
-/// Tries to get the first output of a given graph.
-fn try_get_output(live_graph: &Arc<RwLock<LiveGraph>>) -> Result<Image> {
+/// Tries to get the first output of a given graph, with `exposure` and `normal_map` applied.
+fn try_get_output(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    exposure: f32,
+    normal_map: NormalMap,
+) -> Result<Image> {
     let (output_id, size) = {
         let live_graph = live_graph.read().map_err(|e| anyhow!("{}", e))?;
         let output_id = live_graph.output_ids()[0];
@@ -190,7 +291,52 @@ fn try_get_output(live_graph: &Arc<RwLock<LiveGraph>>) -> Result<Image> {
             depth_or_array_layers: 1,
         },
         TextureDimension::D2,
-        LiveGraph::try_buffer_srgba(live_graph, output_id, SlotId(0))?,
+        if exposure == 0.0 && normal_map == NormalMap::default() {
+            LiveGraph::try_buffer_srgba(live_graph, output_id, SlotId(0))?
+        } else {
+            exposed_srgba(live_graph, output_id, exposure, normal_map)?
+        },
         TextureFormat::Rgba8Unorm,
     ))
 }
+
+/// Like `LiveGraph::try_buffer_srgba`, but the color channels are multiplied by `2^exposure`
+/// before they are clamped, so values above 1 can be seen. The normal map settings are applied
+/// before that.
+fn exposed_srgba(
+    live_graph: &Arc<RwLock<LiveGraph>>,
+    node_id: NodeId,
+    exposure: f32,
+    normal_map: NormalMap,
+) -> Result<Vec<u8>> {
+    let live_graph = live_graph.read().map_err(|e| anyhow!("{}", e))?;
+    let mut pixels = pixels(&live_graph, node_id)?;
+    normal_map.apply(&mut pixels);
+    let pixels = match pixels {
+        Pixels::Rgba(pixels) => pixels,
+        _ => bail!("the thumbnail is not RGBA"),
+    };
+    let multiplier = 2_f32.powf(exposure);
+
+    Ok(pixels
+        .chunks_exact(4)
+        .flat_map(|pixel| {
+            [
+                linear_to_srgb(pixel[0] * multiplier),
+                linear_to_srgb(pixel[1] * multiplier),
+                linear_to_srgb(pixel[2] * multiplier),
+                (pixel[3].clamp(0.0, 1.0) * 255.0).round() as u8,
+            ]
+        })
+        .collect())
+}
+
+fn linear_to_srgb(value: f32) -> u8 {
+    let value = value.clamp(0.0, 1.0);
+    let srgb = if value <= 0.003_130_8 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    };
+    (srgb * 255.0).round() as u8
+}
@@ -1,10 +1,10 @@
 use super::prelude::*;
-use crate::core_translation::Translator;
+use crate::{core_translation::Translator, node_error::set_node_error};
 use std::{
     fmt::Debug,
     sync::{Arc, RwLock},
 };
-use vismut_core::live_graph::LiveGraph;
+use vismut_core::{live_graph::LiveGraph, node_graph::NodeId};
 
 #[derive(Debug)]
 pub struct GuiUndoCommand<T, U>
@@ -17,21 +17,34 @@ where
     to: T,
 }
 
-impl<T: Debug + Clone, U: Translator<T>> UndoCommand for GuiUndoCommand<T, U> {
+impl<T: Debug + Clone, U: Translator<T> + Copy + Into<NodeId>> UndoCommand
+    for GuiUndoCommand<T, U>
+{
     fn forward(&self, world: &mut bevy::prelude::World, _: &mut UndoCommandManager) {
-        if let Some(live_graph) = world.get_resource::<Arc<RwLock<LiveGraph>>>() {
-            if let Ok(mut live_graph) = live_graph.write() {
-                let _ = self.contact_info.set(&mut live_graph, self.to.clone());
-            }
-        }
+        self.set(world, self.to.clone());
     }
 
     fn backward(&self, world: &mut bevy::prelude::World, _: &mut UndoCommandManager) {
-        if let Some(live_graph) = world.get_resource::<Arc<RwLock<LiveGraph>>>() {
-            if let Ok(mut live_graph) = live_graph.write() {
-                let _ = self.contact_info.set(&mut live_graph, self.from.clone());
-            }
-        }
+        self.set(world, self.from.clone());
+    }
+}
+
+impl<T: Debug + Clone, U: Translator<T> + Copy + Into<NodeId>> GuiUndoCommand<T, U> {
+    /// Sets the value, and shows any error on the node.
+    fn set(&self, world: &mut bevy::prelude::World, value: T) {
+        let value_debug = format!("{:?}", value);
+        let result = match world.get_resource::<Arc<RwLock<LiveGraph>>>() {
+            Some(live_graph) => match live_graph.write() {
+                Ok(mut live_graph) => self.contact_info.set(&mut live_graph, value),
+                Err(e) => Err(anyhow::anyhow!("{}", e)),
+            },
+            None => return,
+        };
+
+        let error = result
+            .err()
+            .map(|e| format!("Unable to set {}: {}", value_debug, e));
+        set_node_error(world, self.contact_info.into(), error);
     }
 }
 
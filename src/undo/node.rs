@@ -1,9 +1,15 @@
+use std::sync::{Arc, RwLock};
+
 use bevy::prelude::*;
-use vismut_core::node::Node;
+use vismut_core::{
+    live_graph::LiveGraph,
+    node::Node,
+    node_graph::{NodeId, SlotId},
+};
 
 use crate::{
     mouse_interaction::{active::MakeNothingActive, select::DeselectNode},
-    sync_graph::{self, Edge},
+    sync_graph::{self, has_variable_inputs, Edge, InputCounts},
 };
 
 use super::{edge::RemoveGuiEdge, prelude::*, undo_command_manager::BoxUndoCommand};
@@ -95,3 +101,115 @@ impl RemoveNode {
         Self { node, translation }
     }
 }
+
+/// Sets the number of input slots on a node with variable inputs, like a `Mix` node. You should
+/// generally use `AddInput` or `RemoveInput` instead.
+#[derive(Clone, Copy, Debug)]
+pub struct SetInputCount {
+    pub node_id: NodeId,
+    pub from: usize,
+    pub to: usize,
+}
+impl UndoCommand for SetInputCount {
+    fn forward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        sync_graph::set_gui_input_count(world, self.node_id, self.to);
+    }
+
+    fn backward(&self, world: &mut World, _: &mut UndoCommandManager) {
+        sync_graph::set_gui_input_count(world, self.node_id, self.from);
+    }
+}
+
+/// Returns the current and the minimum number of inputs of a node with variable inputs.
+fn input_count(world: &World, node_id: NodeId) -> Option<(usize, usize)> {
+    let node = world
+        .get_resource::<Arc<RwLock<LiveGraph>>>()?
+        .read()
+        .ok()?
+        .node(node_id)
+        .ok()?;
+
+    if !has_variable_inputs(&node.node_type) {
+        return None;
+    }
+
+    let minimum = node.input_slots().len();
+    let current = world
+        .get_resource::<InputCounts>()
+        .and_then(|input_counts| input_counts.get(&node_id).copied())
+        .unwrap_or(minimum);
+
+    Some((current, minimum))
+}
+
+/// Adds an input slot to a node with variable inputs.
+#[derive(Clone, Copy, Debug)]
+pub struct AddInput(pub NodeId);
+impl UndoCommand for AddInput {
+    fn command_type(&self) -> super::UndoCommandType {
+        super::UndoCommandType::Custom
+    }
+
+    fn forward(&self, world: &mut World, undo_command_manager: &mut UndoCommandManager) {
+        if let Some((current, _)) = input_count(world, self.0) {
+            undo_command_manager.push_front(Box::new(SetInputCount {
+                node_id: self.0,
+                from: current,
+                to: current + 1,
+            }));
+        } else {
+            warn!("tried adding an input to a node that can't have more inputs");
+        }
+    }
+
+    fn backward(&self, _: &mut World, _: &mut UndoCommandManager) {
+        unreachable!("this command is never put on the undo stack");
+    }
+}
+
+/// Removes the last input slot of a node with variable inputs, along with any edge connected to
+/// it.
+#[derive(Clone, Copy, Debug)]
+pub struct RemoveInput(pub NodeId);
+impl UndoCommand for RemoveInput {
+    fn command_type(&self) -> super::UndoCommandType {
+        super::UndoCommandType::Custom
+    }
+
+    fn forward(&self, world: &mut World, undo_command_manager: &mut UndoCommandManager) {
+        let (current, minimum) = match input_count(world, self.0) {
+            Some(counts) => counts,
+            None => {
+                warn!("tried removing an input from a node that can't have fewer inputs");
+                return;
+            }
+        };
+
+        if current <= minimum {
+            warn!("the node already has the minimum number of inputs");
+            return;
+        }
+
+        let last_slot_id = SlotId((current - 1) as u32);
+        let mut commands: Vec<BoxUndoCommand> = Vec::new();
+
+        let mut q_edge = world.query::<&Edge>();
+        for edge in q_edge.iter(world).filter(|edge| {
+            edge.input_slot.node_id == self.0 && edge.input_slot.slot_id == last_slot_id
+        }) {
+            commands.push(Box::new(RemoveGuiEdge(*edge)));
+        }
+
+        commands.push(Box::new(SetInputCount {
+            node_id: self.0,
+            from: current,
+            to: current - 1,
+        }));
+
+        undo_command_manager.push_front_vec(commands);
+    }
+
+    fn backward(&self, _: &mut World, _: &mut UndoCommandManager) {
+        unreachable!("this command is never put on the undo stack");
+    }
+}
@@ -1,7 +1,11 @@
 use std::fmt::Debug;
 
 use super::{prelude::*, UndoCommandType};
-use crate::{instruction::ToolList, AmbiguitySet, CustomStage, ToolState};
+use crate::{
+    instruction::ToolList,
+    keymap::{Action, Keymap},
+    AmbiguitySet, CustomStage, ToolState,
+};
 use bevy::prelude::*;
 
 #[derive(Debug)]
@@ -75,8 +79,9 @@ impl Plugin for UndoPlugin {
     }
 }
 
-fn setup(mut tool_list: ResMut<ToolList>) {
-    tool_list.insert("Ctrl (Shift) Z: Undo & Redo".to_string());
+fn setup(mut tool_list: ResMut<ToolList>, keymap: Res<Keymap>) {
+    tool_list.insert(keymap.help(Action::Undo));
+    tool_list.insert(keymap.help(Action::Redo));
 }
 
 fn undo(
@@ -13,7 +13,12 @@ impl Plugin for VismutPlugin {
         app.insert_non_send_resource(tex_pro)
             .add_state(ToolState::None)
             .add_plugin(crate::export_outputs::ExportOutputsToolPlugin)
+            .add_plugin(crate::export_template::ExportTemplatePlugin)
+            .add_plugin(crate::packing::PackingPlugin)
+            .add_plugin(crate::relink::RelinkToolPlugin)
             .add_plugin(crate::scan_code_input::ScanCodeInputPlugin)
+            .add_plugin(crate::keymap::KeymapPlugin)
+            .add_plugin(crate::text_input::TextInputPlugin)
             .add_plugin(crate::add_tool::AddToolPlugin)
             .add_plugin(crate::drag_drop::WorkspaceDragDropPlugin)
             .add_plugin(crate::mouse_interaction::MouseInteractionPlugin)
@@ -23,19 +28,29 @@ impl Plugin for VismutPlugin {
             .add_plugin(crate::material::MaterialPlugin)
             .add_plugin(crate::sync_graph::SyncGraphPlugin)
             .add_plugin(crate::instruction::InstructionPlugin)
+            .add_plugin(crate::inspector::InspectorPlugin)
             // .add_plugin(crate::deselect_tool::DeselectToolPlugin)
             .add_plugin(crate::delete_tool::DeleteToolPlugin)
+            .add_plugin(crate::clipboard::ClipboardPlugin)
+            .add_plugin(crate::group::GroupPlugin)
             .add_plugin(crate::hotkeys::HotkeysPlugin)
             .add_plugin(crate::hoverable::HoverablePlugin)
             .add_plugin(crate::edit_node::EditNodePlugin)
             .add_plugin(crate::thumbnail::ThumbnailPlugin)
             .add_plugin(crate::export::ExportPlugin)
+            .add_plugin(crate::project::ProjectPlugin)
             .add_plugin(crate::none_tool::NoneToolPlugin)
             .add_plugin(crate::node_state::NodeStatePlugin)
+            .add_plugin(crate::node_error::NodeErrorPlugin)
+            .add_plugin(crate::image_watch::ImageWatchPlugin)
+            .add_plugin(crate::notification::NotificationPlugin)
+            .add_plugin(crate::process_tool::ProcessToolPlugin)
             .add_plugin(crate::thumbnail_state::ThumbnailStatePlugin)
             .add_plugin(crate::undo::undo_command_manager::UndoCommandManagerPlugin)
             .add_plugin(crate::undo::undo_redo_tool::UndoPlugin);
-        // This will be re-enabled when drag and drop is supported in Bevy on Windows.
-        // .add_plugin(crate::drag_drop_import::DragDropImport);
+
+        // Drag and drop conflicts with the file dialogs on Windows.
+        #[cfg(not(target_os = "windows"))]
+        app.add_plugin(crate::drag_drop_import::DragDropImport);
     }
 }
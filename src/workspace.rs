@@ -28,6 +28,8 @@ pub(crate) struct Workspace {
     pub cursor_delta: Vec2,
     pub cursor_moved: bool,
     pub drag: Drag,
+    /// True when the cursor is over a panel, so clicks there don't reach the workspace.
+    pub cursor_over_ui: bool,
 }
 pub(crate) struct WorkspacePlugin;
 
@@ -43,14 +45,16 @@ impl Plugin for WorkspacePlugin {
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn workspace(
     mut er_mouse_motion: EventReader<MouseMotion>,
     mut er_cursor_moved: EventReader<CursorMoved>,
     windows: Res<Windows>,
     mut workspace: ResMut<Workspace>,
     i_mouse_button: Res<Input<MouseButton>>,
-    q_camera: Query<&Transform, With<WorkspaceCamera>>,
+    q_camera: Query<(&Transform, &OrthographicProjection), With<WorkspaceCamera>>,
     mut true_cursor_world: Local<Vec2>,
+    mut cursor_seen: Local<bool>,
 ) {
     let mut event_cursor_delta: Vec2 = Vec2::ZERO;
     for event_motion in er_mouse_motion.iter() {
@@ -60,18 +64,25 @@ fn workspace(
 
     if let Some(event_cursor_screen) = event_cursor_screen {
         workspace.cursor_screen = event_cursor_screen.position;
+        *cursor_seen = true;
+    }
 
-        if let (Some(window), Some(cam_transform)) = (windows.get_primary(), q_camera.iter().last())
+    // The cursor is recalculated every frame, since panning and zooming the camera moves the
+    // cursor in the world even when the mouse is still.
+    let mut cursor_world_changed = false;
+    if *cursor_seen {
+        if let (Some(window), Some((cam_transform, projection))) =
+            (windows.get_primary(), q_camera.iter().last())
         {
-            *true_cursor_world =
-                cursor_to_world(window, cam_transform, event_cursor_screen.position);
+            let cursor_world =
+                cursor_to_world(window, cam_transform, projection, workspace.cursor_screen);
+            cursor_world_changed = cursor_world != *true_cursor_world;
+            *true_cursor_world = cursor_world;
         }
-
-        workspace.cursor_moved = true;
-    } else {
-        workspace.cursor_moved = false;
     }
 
+    workspace.cursor_moved = event_cursor_screen.is_some() || cursor_world_changed;
+
     workspace.cursor_delta = event_cursor_delta;
 
     if !i_mouse_button.pressed(MouseButton::Left) || workspace.drag == Drag::True {
@@ -88,21 +99,31 @@ fn workspace(
         workspace.drag = Drag::Dropping;
     }
 
+    // The threshold is in screen pixels, so it has to be scaled by the zoom.
+    let zoom = q_camera
+        .iter()
+        .last()
+        .map_or(1.0, |(_, projection)| projection.scale);
     if i_mouse_button.pressed(MouseButton::Left)
-        && true_cursor_world.distance(workspace.cursor_world) > DRAG_THRESHOLD
+        && true_cursor_world.distance(workspace.cursor_world) > DRAG_THRESHOLD * zoom
         && workspace.drag == Drag::False
     {
         workspace.drag = Drag::Starting;
     }
 }
 
-fn cursor_to_world(window: &Window, cam_transform: &Transform, cursor_pos: Vec2) -> Vec2 {
+pub(crate) fn cursor_to_world(
+    window: &Window,
+    cam_transform: &Transform,
+    projection: &OrthographicProjection,
+    cursor_pos: Vec2,
+) -> Vec2 {
     // get the size of the window
     let size = Vec2::new(window.width() as f32, window.height() as f32);
 
     // the default orthographic projection is in pixels from the center;
-    // just undo the translation
-    let screen_pos = cursor_pos - size / 2.0;
+    // undo the translation and the zoom
+    let screen_pos = (cursor_pos - size / 2.0) * projection.scale;
 
     // apply the camera transform
     let out = cam_transform.compute_matrix() * screen_pos.extend(0.0).extend(1.0);